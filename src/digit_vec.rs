@@ -0,0 +1,155 @@
+//! Helpers for finite natural numbers stored as little-endian digit vectors.
+//!
+//! Results are always trimmed (no trailing zero digits), so zero is the empty vector.
+
+use crate::discrete::Value;
+use std::cmp::Ordering;
+
+pub(crate) fn trim<Digit: Value>(mut digits: Vec<Digit>) -> Vec<Digit> {
+    while digits.last().is_some_and(|digit| digit.is_zero()) {
+        digits.pop();
+    }
+    digits
+}
+
+pub(crate) fn cmp<Digit: Value>(lhs: &[Digit], rhs: &[Digit]) -> Ordering {
+    let len = lhs.len().max(rhs.len());
+    for i in (0..len).rev() {
+        let lhs_digit = lhs.get(i).copied().unwrap_or(Digit::zero());
+        let rhs_digit = rhs.get(i).copied().unwrap_or(Digit::zero());
+        match lhs_digit.cmp(&rhs_digit) {
+            Ordering::Equal => {}
+            ordering => return ordering,
+        }
+    }
+    Ordering::Equal
+}
+
+pub(crate) fn add<Digit: Value>(lhs: &[Digit], rhs: &[Digit]) -> Vec<Digit> {
+    let mut result = Vec::with_capacity(lhs.len().max(rhs.len()) + 1);
+    let mut carry = false;
+    for i in 0..lhs.len().max(rhs.len()) {
+        let lhs_digit = lhs.get(i).copied().unwrap_or(Digit::zero());
+        let rhs_digit = rhs.get(i).copied().unwrap_or(Digit::zero());
+        let (digit_sum, digit_carry) = lhs_digit.add_carry(rhs_digit);
+        let (full_sum, full_carry) = digit_sum.add_carry(Digit::from_bool(carry));
+        result.push(full_sum);
+        carry = digit_carry || full_carry;
+    }
+    if carry {
+        result.push(Digit::one());
+    }
+    trim(result)
+}
+
+/// Subtracts `rhs` from `lhs`, which must be at least as big.
+pub(crate) fn sub<Digit: Value>(lhs: &[Digit], rhs: &[Digit]) -> Vec<Digit> {
+    let mut result = Vec::with_capacity(lhs.len());
+    let mut borrow = false;
+    for i in 0..lhs.len().max(rhs.len()) {
+        let lhs_digit = lhs.get(i).copied().unwrap_or(Digit::zero());
+        let rhs_digit = rhs.get(i).copied().unwrap_or(Digit::zero());
+        let (digit_difference, digit_borrow) = lhs_digit.sub_borrow(rhs_digit);
+        let (full_difference, full_borrow) = digit_difference.sub_borrow(Digit::from_bool(borrow));
+        result.push(full_difference);
+        borrow = digit_borrow || full_borrow;
+    }
+    debug_assert!(!borrow, "subtracted a bigger number from a smaller one");
+    trim(result)
+}
+
+pub(crate) fn mul_digit<Digit: Value>(lhs: &[Digit], rhs: Digit) -> Vec<Digit> {
+    let mut result = Vec::with_capacity(lhs.len() + 1);
+    let mut carry = Digit::zero();
+    for &digit in lhs {
+        let (low, high) = digit.mul_overflow(rhs);
+        let (sum, sum_carry) = low.add_carry(carry);
+        result.push(sum);
+        // `high` is at most p - 2 whenever a carry can happen, so this never overflows
+        carry = high.add_carry(Digit::from_bool(sum_carry)).0;
+    }
+    result.push(carry);
+    trim(result)
+}
+
+/// Below this many digits, [`mul`] uses schoolbook multiplication instead of Karatsuba.
+const KARATSUBA_THRESHOLD: usize = 64;
+
+pub(crate) fn mul<Digit: Value>(lhs: &[Digit], rhs: &[Digit]) -> Vec<Digit> {
+    if lhs.len().min(rhs.len()) < KARATSUBA_THRESHOLD {
+        return schoolbook_mul(lhs, rhs);
+    }
+    // (low + p^half high)(low' + p^half high') with three multiplications instead of four
+    let half = lhs.len().max(rhs.len()) / 2;
+    let (lhs_low, lhs_high) = lhs.split_at(half.min(lhs.len()));
+    let (rhs_low, rhs_high) = rhs.split_at(half.min(rhs.len()));
+    let low = mul(lhs_low, rhs_low);
+    let high = mul(lhs_high, rhs_high);
+    let middle = mul(&add(lhs_low, lhs_high), &add(rhs_low, rhs_high));
+    let middle = sub(&sub(&middle, &low), &high);
+    let mut result = low;
+    add_shifted(&mut result, &middle, half);
+    add_shifted(&mut result, &high, 2 * half);
+    trim(result)
+}
+
+/// Roughly how many digit multiplications [`mul`] does for two `len`-digit numbers.
+pub(crate) fn mul_cost(len: usize) -> usize {
+    if len < KARATSUBA_THRESHOLD {
+        len * len
+    } else {
+        3 * mul_cost(len.div_ceil(2))
+    }
+}
+
+fn schoolbook_mul<Digit: Value>(lhs: &[Digit], rhs: &[Digit]) -> Vec<Digit> {
+    if lhs.is_empty() || rhs.is_empty() {
+        return vec![];
+    }
+    let mut result = vec![Digit::zero(); lhs.len() + rhs.len()];
+    for (i, &lhs_digit) in lhs.iter().enumerate() {
+        if lhs_digit.is_zero() {
+            continue;
+        }
+        let mut carry = Digit::zero();
+        for (j, &rhs_digit) in rhs.iter().enumerate() {
+            let (low, high) = lhs_digit.mul_overflow(rhs_digit);
+            let (sum, low_carry) = low.add_carry(carry);
+            let (sum, result_carry) = sum.add_carry(result[i + j]);
+            result[i + j] = sum;
+            // the whole column is at most p^2 - 1, so the carry still fits in one digit
+            carry = high
+                .add_carry(Digit::from_bool(low_carry))
+                .0
+                .add_carry(Digit::from_bool(result_carry))
+                .0;
+        }
+        result[i + rhs.len()] = carry;
+    }
+    trim(result)
+}
+
+/// Adds `value * p^offset` to `result` in place.
+pub(crate) fn add_shifted<Digit: Value>(result: &mut Vec<Digit>, value: &[Digit], offset: usize) {
+    if result.len() < offset + value.len() {
+        result.resize(offset + value.len(), Digit::zero());
+    }
+    let mut carry = false;
+    let mut index = offset;
+    for &digit in value {
+        let (digit_sum, digit_carry) = result[index].add_carry(digit);
+        let (full_sum, full_carry) = digit_sum.add_carry(Digit::from_bool(carry));
+        result[index] = full_sum;
+        carry = digit_carry || full_carry;
+        index += 1;
+    }
+    while carry {
+        if index == result.len() {
+            result.push(Digit::one());
+            carry = false;
+        } else {
+            (result[index], carry) = result[index].add_carry(Digit::one());
+        }
+        index += 1;
+    }
+}
@@ -89,6 +89,10 @@ impl<const BASE: u8> AddGroupU8<BASE> {
     pub fn new_vec(values: Vec<u8>) -> Option<Vec<Self>> {
         values.iter().map(|x| Self::new(*x)).collect()
     }
+
+    pub const fn value(self) -> u8 {
+        self.val
+    }
 }
 
 impl<const BASE: u8> Zero for AddGroupU8<BASE> {
@@ -137,7 +141,7 @@ impl<const BASE: u8> Add for AddGroupU8<BASE> {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        Self::new((self.val + rhs.val).rem_euclid(BASE)).unwrap()
+        Self::new(((self.val as u16 + rhs.val as u16) % BASE as u16) as u8).unwrap()
     }
 }
 
@@ -145,7 +149,7 @@ impl<const BASE: u8> Sub for AddGroupU8<BASE> {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        Self::new((self.val - rhs.val).rem_euclid(BASE)).unwrap()
+        Self::new((self.val as i16 - rhs.val as i16).rem_euclid(BASE as i16) as u8).unwrap()
     }
 }
 
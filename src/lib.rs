@@ -0,0 +1,29 @@
+//! Lazily evaluated p-adic numbers. The binary is a small REPL on top of this library.
+
+pub mod digit_vec;
+pub mod discrete;
+pub mod padic;
+pub mod padic_add;
+#[cfg(feature = "num-bigint")]
+pub mod padic_bigint;
+pub mod padic_deep;
+pub mod padic_div;
+pub mod padic_fixed_point;
+pub mod padic_int;
+pub mod padic_lang;
+pub mod padic_laurent;
+pub mod padic_metric;
+pub mod padic_mul;
+pub mod padic_nary;
+#[cfg(feature = "num-traits")]
+pub mod padic_num_traits;
+pub mod padic_ops;
+pub mod padic_periodic;
+pub mod padic_power_series;
+pub mod padic_primitive;
+pub mod padic_residue;
+pub mod padic_series;
+pub mod padic_shift;
+pub mod padic_sub;
+#[cfg(test)]
+mod padic_test_util;
@@ -1,9 +1,10 @@
 #![feature(str_split_whitespace_remainder)]
 extern crate core;
 
-use crate::discrete::AddGroupU8;
-use crate::padic::PadicNumber;
-use crate::padic_lang::parse_padic;
+use p_adic::discrete::AddGroupU8;
+use p_adic::padic::{PadicNumber, StaticPadicNumber};
+use p_adic::padic_lang::{parse_laurent, parse_padic};
+use p_adic::padic_laurent::LaurentSeries;
 use std::collections::HashMap;
 use std::io::{stdin, BufRead};
 // #![feature(generic_const_exprs)]
@@ -16,25 +17,18 @@ use std::io::{stdin, BufRead};
 // #![feature(const_ops)]
 // #![feature(const_cmp)]
 
-pub mod discrete;
-pub mod padic;
-pub mod padic_add;
-pub mod padic_div;
-pub mod padic_lang;
-pub mod padic_mul;
-pub mod padic_primitive;
-pub mod padic_sub;
-
 const BASE: u8 = 2;
 
+#[allow(clippy::almost_complete_range)]
 fn main() -> Result<(), String> {
     let mut length = 10;
     let stdin = stdin();
-    let mut saved_values: HashMap<String, PadicNumber<AddGroupU8<BASE>>> = HashMap::new();
+    let mut saved_values: HashMap<String, StaticPadicNumber<AddGroupU8<BASE>>> = HashMap::new();
+    let mut saved_series: HashMap<String, LaurentSeries<'static, AddGroupU8<BASE>>> = HashMap::new();
     for line in stdin.lock().lines() {
         match line {
             Ok(equation) => {
-                if let Some(x) = equation.chars().nth(0) {
+                if let Some(x) = equation.chars().next() {
                     match x {
                         'e' => {
                             if equation.len() < 3 {
@@ -43,6 +37,13 @@ fn main() -> Result<(), String> {
                                 evaluate(&equation[2..], &mut saved_values, length)
                             }
                         },
+                        'f' => {
+                            if equation.len() < 3 {
+                                println!("Bad format, expecting: f <expression>. Ex: f 0 1 -");
+                            } else {
+                                evaluate_laurent(&equation[2..], &mut saved_series, length)
+                            }
+                        },
                         's' => if equation.len() < 3 {
                             println!(
                                 "Bad format, expecting: s <variable name starts with lowercase letter> <expression>. Ex: s my_num 0 1 -"
@@ -52,19 +53,18 @@ fn main() -> Result<(), String> {
                             let var = split.next();
                             let equation = split.remainder();
                             if let Some(var) = var
-                                && let Some(ch) = var.chars().nth(0)
-                                && 'a' <= ch
-                                && ch <= 'z'
+                                && let Some(ch) = var.chars().next()
+                                && ch.is_ascii_lowercase()
                                 && let Some(equation) = equation
                             {
-                                match parse_padic::<BASE>(&equation, &saved_values) {
+                                match parse_padic::<BASE>(equation, &saved_values) {
                                     Ok(number) => {
                                         saved_values.insert(var.to_owned(), number.clone());
                                         println!(
                                             "{} = {} : {}",
                                             var,
                                             equation,
-                                            number.as_view(length)
+                                            describe(&number, length)
                                         )
                                     }
                                     Err(e) => println!("Error: {}", e),
@@ -88,13 +88,13 @@ fn main() -> Result<(), String> {
                         }
                         'v' => {
                             for (name, val) in saved_values.iter() {
-                                println!("{} = {}", name, val.as_view(length));
+                                println!("{} = {}", name, describe(val, length));
                             }
                         }
                         'q' => return Ok(()),
                         'h' => {
                             println!(
-                                "Available commands: e (evaluate), s (set), l (set the length), v (list variables), q (quit), h (help (you're here!))"
+                                "Available commands: e (evaluate), f (evaluate in F_p((t)), carry-free), s (set), l (set the length), v (list variables), q (quit), h (help (you're here!))"
                             );
                             println!("You are currently using the p-adic program with p = {}!", BASE);
                         },
@@ -112,12 +112,33 @@ fn main() -> Result<(), String> {
     Ok(())
 }
 
-fn evaluate(equation: &str, saved_values: &mut HashMap<String, PadicNumber<AddGroupU8<BASE>>>, length: isize) {
-    match parse_padic::<BASE>(&equation, &saved_values) {
+fn evaluate(equation: &str, saved_values: &mut HashMap<String, StaticPadicNumber<AddGroupU8<BASE>>>, length: isize) {
+    match parse_padic::<BASE>(equation, saved_values) {
         Ok(number) => {
             saved_values.insert("".to_owned(), number.clone());
-            println!("{} : {}", &equation, number.as_view(length))
+            println!("{} : {}", equation, describe(&number, length))
         }
         Err(e) => println!("Error: {}", e),
     }
 }
+
+fn evaluate_laurent(
+    equation: &str,
+    saved_series: &mut HashMap<String, LaurentSeries<'static, AddGroupU8<BASE>>>,
+    length: isize,
+) {
+    match parse_laurent::<BASE>(equation, saved_series) {
+        Ok(series) => {
+            saved_series.insert("".to_owned(), series.clone());
+            println!("{} : {}", equation, series.as_view(length))
+        }
+        Err(e) => println!("Error: {}", e),
+    }
+}
+
+fn describe(number: &PadicNumber<AddGroupU8<BASE>>, length: isize) -> String {
+    match number.exact() {
+        Some(exact) => format!("{} (exact: {})", number.as_view(length), exact),
+        None => number.as_view(length).to_string(),
+    }
+}
@@ -1,14 +1,39 @@
 pub use crate::discrete::Value;
+use crate::padic_deep::{read_operand, read_outermost};
+use crate::padic_nary::TermList;
+use crate::padic_periodic::EventuallyPeriodicPadic;
+use std::cell::{Cell, OnceCell};
 use std::fmt::Display;
+use std::ops::Range;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
 use std::rc::Rc;
 
 pub struct PadicNumber<'a, Digit: Value> {
     pub(crate) value: Rc<dyn PadicAccessor<'a, Digit> + 'a>,
+    /// The same number as `value`, when it's known to be rational.
+    pub(crate) exact: Option<Rc<EventuallyPeriodicPadic<Digit>>>,
 }
 
+/// A number that doesn't borrow anything.
+///
+/// Every node owns its operands (through an `Rc`), so any number built from owned values and
+/// `'static` closures is one of these, and can be stored and passed around freely, like the
+/// saved values of the calculator. The lifetime of a [`PadicNumber`] only gets shorter when a
+/// closure it's built from, like the digits of [`PadicNumber::from_fn`], borrows something.
+pub type StaticPadicNumber<Digit> = PadicNumber<'static, Digit>;
+
 impl<'a, Digit: Value + 'a> PadicNumber<'a, Digit> {
     pub fn new_from_rc(value: Rc<dyn PadicAccessor<'a, Digit> + 'a>) -> PadicNumber<'a, Digit> {
-        PadicNumber { value }
+        PadicNumber { value, exact: None }
+    }
+
+    /// Wraps a rational number, which arithmetic then keeps exact for as long as it can.
+    pub fn from_exact(value: EventuallyPeriodicPadic<Digit>) -> PadicNumber<'a, Digit> {
+        let value = Rc::new(value);
+        PadicNumber {
+            value: value.clone(),
+            exact: Some(value),
+        }
     }
 
     pub fn new<Accessor: PadicAccessor<'a, Digit> + 'a>(
@@ -24,23 +49,163 @@ impl<'a, Digit: Value + 'a> PadicNumber<'a, Digit> {
         }
     }
 
+    pub fn get_digit(&self, index: isize) -> Digit {
+        read_outermost(self, || self.value.get_digit(index))
+    }
+
+    /// See [`PadicAccessor::fill_digits`].
+    pub fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        read_outermost(self, || self.value.fill_digits(range.clone(), digits));
+    }
+
+    /// Every digit below the scale is zero, but the digit at the scale may be too: it's only a lower
+    /// bound on the valuation (see [`Self::valuation`]).
     pub fn get_scale(&self) -> isize {
         self.value.get_scale()
     }
+
+    /// See [`PadicAccessor::settle_scale`].
+    pub(crate) fn settle_scale(&self) -> bool {
+        self.value.settle_scale()
+    }
+
+    /// Builds a node from `operands` with `build` once it's read, for operands whose scales
+    /// aren't settled yet, as building a node looks at their scales.
+    pub(crate) fn build_when_read(
+        operands: Vec<PadicNumber<'a, Digit>>,
+        build: impl Fn(&[PadicNumber<'a, Digit>]) -> PadicNumber<'a, Digit> + 'a,
+    ) -> PadicNumber<'a, Digit> {
+        PadicNumber::new(UnbuiltPadicNumber { operands, build: Box::new(build), built: OnceCell::new() })
+    }
+
+    /// The index of the lowest nonzero digit, or `None` if every digit below `view_size` is zero.
+    pub fn valuation(&self, view_size: isize) -> Option<isize> {
+        read_outermost(self, || self.value.get_valuation(view_size))
+    }
+
+    /// The terms of this number if it's a sum. Sums are flattened even when something else
+    /// refers to them: that computes their terms a second time instead of reusing their digits,
+    /// but `acc = &acc + &x` in a loop would otherwise nest as deep as the loop is long.
+    pub(crate) fn sum_terms(&self) -> Option<Rc<TermList<'a, Digit>>> {
+        self.value.sum_terms()
+    }
+
+    /// Like [`Self::sum_terms`], for the factors of a product.
+    pub(crate) fn product_factors(&self) -> Option<Rc<TermList<'a, Digit>>> {
+        self.value.product_factors()
+    }
+
+    /// The exact value of this number, if it's known to be rational.
+    ///
+    /// Numbers made from integers and rationals are exact, and arithmetic on exact numbers stays
+    /// exact as long as the result can be worked out within [`EXACT_DIGIT_LIMIT`] digits. Anything
+    /// else, like a number made from digits or anything computed from one, is `None`, even if it
+    /// happens to be rational.
+    ///
+    /// [`EXACT_DIGIT_LIMIT`]: crate::padic_periodic::EXACT_DIGIT_LIMIT
+    pub fn exact(&self) -> Option<&EventuallyPeriodicPadic<Digit>> {
+        self.exact.as_deref()
+    }
+
+    /// Compares two numbers, or returns `None` when that can't be decided (which is whenever
+    /// they aren't both exact, unless they are the very same number).
+    pub fn try_eq(&self, other: &Self) -> Option<bool> {
+        match (self.exact(), other.exact()) {
+            (Some(lhs), Some(rhs)) => Some(lhs == rhs),
+            _ if Rc::ptr_eq(&self.value, &other.value) => Some(true),
+            _ => None,
+        }
+    }
 }
 
 impl<'a, Digit: Value> Clone for PadicNumber<'a, Digit> {
     fn clone(&self) -> PadicNumber<'a, Digit> {
-        PadicNumber::new_from_rc(self.value.clone())
+        PadicNumber {
+            value: self.value.clone(),
+            exact: self.exact.clone(),
+        }
     }
 }
 
+impl<'a, Digit: Value> From<EventuallyPeriodicPadic<Digit>> for PadicNumber<'a, Digit> {
+    fn from(value: EventuallyPeriodicPadic<Digit>) -> Self {
+        PadicNumber::from_exact(value)
+    }
+}
+
+/// A node that computes the digits of a number.
+///
+/// Implementations are free to compute digits lazily and cache them, but have to give the same
+/// digit every time it's asked for. Computing a digit can end up asking the same node for a
+/// digit again, when the node is part of a number defined in terms of itself (see
+/// [`PadicNumber::fixed_point`]). Such a nested read has to give the right digit, or raise
+/// [`PadicError::IllFounded`] when the digit depends on itself; it must never give a digit made
+/// up from a half-built cache.
+///
+/// Reading a digit can also fail, which a node reports by calling [`raise`] with a
+/// [`PadicError`] that [`PadicNumber::try_fill_digits`] turns back into a `Result`, so a node has
+/// to stay usable afterwards. A node that takes its cache out of a `Cell` for the duration of a
+/// call does it with a [`CellGuard`], which puts the cache back even when an operand raises an
+/// error, so the digits computed so far aren't lost. The cache then has to be consistent
+/// whenever an operand is read: read what a step needs first, then update the cache. While the
+/// cache is out, a nested read finds it empty: the node either starts over there, or keeps the
+/// cache in an `Option` and raises [`PadicError::IllFounded`] when it finds `None`.
 pub trait PadicAccessor<'a, Digit: Value + 'a>
 where
     Self: 'a,
 {
     fn get_digit(&self, index: isize) -> Digit;
+
+    /// Writes the digits in `range` into `digits`, which is exactly as long as the range.
+    ///
+    /// Accessors with a cache should override this to take the cache once for the whole block.
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        for (digit, index) in digits.iter_mut().zip(range) {
+            *digit = self.get_digit(index);
+        }
+    }
+
+    /// Every digit below the scale is zero. The scale doesn't have to be the lowest nonzero digit
+    /// (that would mean computing digits up front), so it may get closer to it as more digits are
+    /// computed.
     fn get_scale(&self) -> isize;
+
+    /// Whether [`Self::get_scale`] can answer without reading digits that may never end, after
+    /// reading a bounded number of them. A quotient only knows its scale once it has counted the
+    /// low zeros of its divisor, which never ends for a lazy zero, so arithmetic on a number that
+    /// isn't settled waits until it's read (see [`PadicNumber::build_when_read`]).
+    fn settle_scale(&self) -> bool {
+        true
+    }
+
+    /// Finds the lowest nonzero digit by looking at the digits from the scale up to `limit`.
+    fn get_valuation(&self, limit: isize) -> Option<isize> {
+        (self.get_scale()..limit).find(|&i| !self.get_digit(i).is_zero())
+    }
+
+    /// The terms of this node if it's a sum, so that adding to it can extend the sum instead of
+    /// nesting it. Binary nodes only build the list when this is called.
+    fn sum_terms(&self) -> Option<Rc<TermList<'a, Digit>>> {
+        None
+    }
+
+    /// Like [`Self::sum_terms`], for the factors of a product.
+    fn product_factors(&self) -> Option<Rc<TermList<'a, Digit>>> {
+        None
+    }
+
+    /// The numbers this node reads digits of. Deep graphs are read by walking these instead of
+    /// recursing (see [`crate::padic_deep`]), which only works for nodes that list them.
+    fn operands(&self) -> Vec<PadicNumber<'a, Digit>> {
+        vec![]
+    }
+
+    /// Moves the operands out of this node when it's being dropped, so they can be dropped
+    /// without recursing. The node is only dropped afterwards.
+    fn take_operands(&mut self) -> Vec<PadicNumber<'a, Digit>> {
+        vec![]
+    }
+
     fn to_dyn(self) -> PadicNumber<'a, Digit>
     where
         Self: Sized,
@@ -49,20 +214,78 @@ where
     }
 }
 
+/// The digits of a p-adic integer, from digit 0 up.
+///
+/// This is what most arithmetic nodes work with: the operands are lined up at a common scale with
+/// [`PadicNumberScaler`], and the result is put back at that scale with [`PadicIntegerToNumber`].
+/// The same rules as for [`PadicAccessor`] apply.
+///
+/// The scale of a number is only a lower bound on its lowest nonzero digit (see
+/// [`PadicAccessor::get_scale`]), so the integers made this way can start with any number of zero
+/// digits, which division for one has to skip before it can invert digit 0.
 pub trait PadicIntegerAccessor<'a, Digit: Value + 'a>
 where
     Self: 'a,
 {
     fn get_integer_digit(&self, index: usize) -> Digit;
+
+    /// Like [`PadicAccessor::fill_digits`], for integer digits.
+    fn fill_integer_digits(&self, range: Range<usize>, digits: &mut [Digit]) {
+        for (digit, index) in digits.iter_mut().zip(range) {
+            *digit = self.get_integer_digit(index);
+        }
+    }
+}
+
+/// Fills `digits` for `range` from an integer whose digit 0 sits at `scale`, with zeros below it.
+pub fn fill_scaled_digits<'a, Digit: Value + 'a>(
+    inner: &dyn PadicIntegerAccessor<'a, Digit>,
+    scale: isize,
+    range: Range<isize>,
+    digits: &mut [Digit],
+) {
+    let zeros = (range.end.min(scale) - range.start).clamp(0, digits.len() as isize) as usize;
+    digits[..zeros].fill(Digit::zero());
+    if zeros < digits.len() {
+        let start = (range.start + zeros as isize - scale) as usize;
+        inner.fill_integer_digits(start..start + digits.len() - zeros, &mut digits[zeros..]);
+    }
+}
+
+/// How many of the digits a sum has computed so far, from the bottom, are all zero, which is how
+/// far its scale can be moved up.
+///
+/// Digits can cancel out, so a sum can start with any number of zeros, and moving its scale past
+/// them up front could take forever. Instead the scale only moves past the zeros that have been
+/// computed, and gets closer to the lowest nonzero digit as more digits are.
+#[derive(Default)]
+pub(crate) struct LeadingZeros(Cell<usize>);
+
+impl LeadingZeros {
+    /// Records integer digit `index`, which comes right after the ones recorded so far.
+    pub(crate) fn push<Digit: Value>(&self, index: usize, digit: &Digit) {
+        if digit.is_zero() && self.0.get() == index {
+            self.0.set(index + 1);
+        }
+    }
+
+    pub(crate) fn get(&self) -> usize {
+        self.0.get()
+    }
 }
 
-pub(crate) struct PadicNumberScaler<'a, Digit: Value> {
+/// Reads digit `scale` of a number as digit 0 of an integer.
+///
+/// Digits below `scale` are dropped, so `scale` is normally at most the scale of the number. That
+/// scale is only a lower bound, so digit 0 of the integer can still be zero. No digits are cached
+/// here; the number caches its own.
+pub struct PadicNumberScaler<'a, Digit: Value> {
     inner: PadicNumber<'a, Digit>,
     scale: isize,
 }
 
 impl<'a, Digit: Value + 'a> PadicNumberScaler<'a, Digit> {
-    pub(crate) fn new(number: &PadicNumber<'a, Digit>, scale: isize) -> PadicNumberScaler<'a, Digit> {
+    pub fn new(number: &PadicNumber<'a, Digit>, scale: isize) -> PadicNumberScaler<'a, Digit> {
         PadicNumberScaler {
             inner: number.clone(),
             scale,
@@ -72,35 +295,103 @@ impl<'a, Digit: Value + 'a> PadicNumberScaler<'a, Digit> {
 
 impl<'a, Digit: Value + 'a> PadicIntegerAccessor<'a, Digit> for PadicNumberScaler<'a, Digit> {
     fn get_integer_digit(&self, index: usize) -> Digit {
-        self.inner.value.get_digit(index as isize + self.scale)
+        let index = index as isize + self.scale;
+        read_operand(&self.inner, index..index + 1, || self.inner.value.get_digit(index))
+    }
+
+    fn fill_integer_digits(&self, range: Range<usize>, digits: &mut [Digit]) {
+        let range = range.start as isize + self.scale..range.end as isize + self.scale;
+        read_operand(&self.inner, range.clone(), || self.inner.value.fill_digits(range, digits));
     }
 }
 
-pub(crate) struct PadicIntegerToNumber<'a, Digit: Value> {
-    inner: Rc<dyn PadicIntegerAccessor<'a, Digit>>,
+/// Puts digit 0 of an integer at index `scale` of a number, which then has that scale.
+///
+/// Like [`PadicNumberScaler`], this only forwards, so caching is up to the integer.
+pub struct PadicIntegerToNumber<'a, Digit: Value> {
+    inner: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
     scale: isize,
 }
 
 impl<'a, Digit: Value + 'a> PadicIntegerToNumber<'a, Digit> {
-    pub(crate) fn new(inner: Rc<dyn PadicIntegerAccessor<'a, Digit>>, scale: isize) -> Self {
+    pub fn new(inner: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>, scale: isize) -> Self {
         PadicIntegerToNumber { inner, scale }
     }
 }
 
 impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for PadicIntegerToNumber<'a, Digit> {
     fn get_digit(&self, index: isize) -> Digit {
-        if index + self.scale < 0 {
+        if index < self.scale {
             Digit::zero()
         } else {
-            self.inner.get_integer_digit((index + self.scale) as usize)
+            self.inner.get_integer_digit((index - self.scale) as usize)
         }
     }
 
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        fill_scaled_digits(self.inner.as_ref(), self.scale, range, digits);
+    }
+
     fn get_scale(&self) -> isize {
         self.scale
     }
 }
 
+type Builder<'a, Digit> = dyn Fn(&[PadicNumber<'a, Digit>]) -> PadicNumber<'a, Digit> + 'a;
+
+/// A node whose operands weren't settled (see [`PadicAccessor::settle_scale`]) when it was asked
+/// for, built the first time it's read.
+pub struct UnbuiltPadicNumber<'a, Digit: Value> {
+    operands: Vec<PadicNumber<'a, Digit>>,
+    build: Box<Builder<'a, Digit>>,
+    built: OnceCell<PadicNumber<'a, Digit>>,
+}
+
+impl<'a, Digit: Value + 'a> UnbuiltPadicNumber<'a, Digit> {
+    fn built(&self) -> &PadicNumber<'a, Digit> {
+        self.built.get_or_init(|| {
+            // reading a number needs the scales of its operands anyway
+            for operand in &self.operands {
+                operand.get_scale();
+            }
+            (self.build)(&self.operands)
+        })
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for UnbuiltPadicNumber<'a, Digit> {
+    fn get_digit(&self, index: isize) -> Digit {
+        self.built().value.get_digit(index)
+    }
+
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        self.built().value.fill_digits(range, digits);
+    }
+
+    fn get_scale(&self) -> isize {
+        self.built().get_scale()
+    }
+
+    /// Doesn't try to settle the operands: a chain of nodes waiting on the same quotient would
+    /// try them all for every node added to it.
+    fn settle_scale(&self) -> bool {
+        self.built.get().is_some_and(PadicNumber::settle_scale)
+    }
+
+    fn operands(&self) -> Vec<PadicNumber<'a, Digit>> {
+        match self.built.get() {
+            Some(built) => vec![built.clone()],
+            None => self.operands.clone(),
+        }
+    }
+
+    fn take_operands(&mut self) -> Vec<PadicNumber<'a, Digit>> {
+        let mut operands = std::mem::take(&mut self.operands);
+        operands.extend(self.built.take());
+        operands
+    }
+}
+
 pub struct PadicNumberView<'a, Digit: Value> {
     value: Rc<dyn PadicAccessor<'a, Digit> + 'a>,
     view_size: isize,
@@ -108,29 +399,272 @@ pub struct PadicNumberView<'a, Digit: Value> {
 
 impl<'a, Digit: Value + 'a> Display for PadicNumberView<'a, Digit> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for i in (0..self.view_size).rev() {
-            write!(f, "{}", self.value.get_digit(i))?;
-        }
-        if self.value.get_scale() < 0 {
-            let mut digit_buffer = vec![];
-            for i in self.value.get_scale()..0 {
-                let value = self.value.get_digit(i);
-                if !(digit_buffer.is_empty() && value.is_zero()) {
-                    digit_buffer.push(value);
-                }
-            }
-            if digit_buffer.len() > 0 {
-                write!(f, ".")?;
-                for digit in digit_buffer.iter().rev() {
-                    write!(f, "{}", digit)?;
-                }
+        let mut digits = vec![Digit::zero(); self.view_size.max(0) as usize];
+        self.value.fill_digits(0..self.view_size.max(0), &mut digits);
+        for digit in digits.iter().rev() {
+            write!(f, "{}", digit)?;
+        }
+        if let Some(valuation) = self.value.get_valuation(0) {
+            write!(f, ".")?;
+            let mut digits = vec![Digit::zero(); -valuation as usize];
+            self.value.fill_digits(valuation..0, &mut digits);
+            for digit in digits.iter().rev() {
+                write!(f, "{}", digit)?;
             }
         }
         Ok(())
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PadicError {
     ValuesGreaterThanOrEqualToP,
+    NotAnInteger,
+    OutOfRange,
+    NotConvergent,
+    /// A digit of a self-referential number depends on itself.
+    IllFounded,
+    /// A string has a character that isn't a digit.
+    InvalidDigit,
+    /// A residue mod p^`precision` doesn't agree with the residues before it.
+    IncompatibleResidues { precision: usize },
+    /// Residue `n` of [`PadicNumber::from_residues`] is only known mod a lower power of p than
+    /// p^`n`.
+    ResidueBelowPrecision { n: usize },
+    DivisionByZero,
+    /// Coefficient `index` of a power series has a lower valuation than it was said to have.
+    CoefficientBelowBound { index: usize },
+    /// Term `index` of a series has a lower valuation than it was said to have.
+    TermBelowBound { index: usize },
+    /// The valuation bound of term `index` of a series is lower than the bound of the term
+    /// before it.
+    DecreasingBound { index: usize },
+    /// The digits of a lazy number below `view_size` don't settle the answer.
+    Undecided { view_size: isize },
+    /// A shift or product moves a digit past the indices an `isize` can hold.
+    ScaleOverflow,
+}
+
+impl Display for PadicError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PadicError::ValuesGreaterThanOrEqualToP => write!(f, "digits have to be less than p"),
+            PadicError::NotAnInteger => write!(f, "not a p-adic integer"),
+            PadicError::OutOfRange => write!(f, "out of range"),
+            PadicError::NotConvergent => write!(f, "doesn't converge"),
+            PadicError::IllFounded => write!(f, "a digit of a self-referential number depends on itself"),
+            PadicError::InvalidDigit => write!(f, "invalid digit"),
+            PadicError::IncompatibleResidues { precision } => {
+                write!(f, "the residue mod p^{} doesn't agree with the residues before it", precision)
+            }
+            PadicError::ResidueBelowPrecision { n } => {
+                write!(f, "residue {} is known mod a lower power of p than p^{}", n, n)
+            }
+            PadicError::DivisionByZero => write!(f, "attempt to divide by zero"),
+            PadicError::CoefficientBelowBound { index } => {
+                write!(f, "coefficient {} of the power series is below its valuation bound", index)
+            }
+            PadicError::TermBelowBound { index } => {
+                write!(f, "term {} of the series is below its valuation bound", index)
+            }
+            PadicError::DecreasingBound { index } => {
+                write!(f, "the valuation bound of term {} of the series is lower than the one before it", index)
+            }
+            PadicError::Undecided { view_size } => {
+                write!(f, "the digits below {} don't decide the answer", view_size)
+            }
+            PadicError::ScaleOverflow => write!(f, "a digit index overflows"),
+        }
+    }
+}
+
+impl std::error::Error for PadicError {}
+
+thread_local! {
+    /// How many [`PadicNumber::try_fill_digits`] calls are running on this thread.
+    static CATCHING: Cell<usize> = const { Cell::new(0) };
+}
+
+impl<'a, Digit: Value + 'a> PadicNumber<'a, Digit> {
+    /// Like [`Self::fill_digits`], but returns the error when computing the digits fails with a
+    /// [`PadicError`] instead of panicking.
+    pub fn try_fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) -> Result<(), PadicError> {
+        struct Catching;
+        impl Drop for Catching {
+            fn drop(&mut self) {
+                CATCHING.set(CATCHING.get() - 1);
+            }
+        }
+
+        CATCHING.set(CATCHING.get() + 1);
+        let _catching = Catching;
+        catch_unwind(AssertUnwindSafe(|| self.fill_digits(range, digits))).map_err(|payload| {
+            match payload.downcast::<PadicError>() {
+                Ok(error) => *error,
+                Err(payload) => resume_unwind(payload),
+            }
+        })
+    }
+}
+
+/// Reports an error found while computing digits, which can't return a `Result`.
+///
+/// Under [`PadicNumber::try_fill_digits`] this unwinds with the error as the payload, for it to
+/// return. Anywhere else it's an ordinary panic with the error as the message.
+pub fn raise(error: PadicError) -> ! {
+    if CATCHING.get() > 0 {
+        resume_unwind(Box::new(error))
+    } else {
+        panic!("{}", error)
+    }
+}
+
+/// A digit index worked out with `checked_add` or `checked_sub`, raising
+/// [`PadicError::ScaleOverflow`] when it overflowed instead of saturating or wrapping into a
+/// different number. Every sum or difference of scales, exact or lazy, goes through this.
+pub(crate) fn checked_index(index: Option<isize>) -> isize {
+    index.unwrap_or_else(|| raise(PadicError::ScaleOverflow))
+}
+
+/// A value taken out of a `Cell`, which goes back in when this is dropped, so a panic while it's
+/// out (like a [`PadicError`] that [`PadicNumber::try_fill_digits`] catches) doesn't lose it.
+pub struct CellGuard<'c, T: Default> {
+    cell: &'c Cell<T>,
+    pub value: T,
+}
+
+impl<'c, T: Default> CellGuard<'c, T> {
+    pub fn take(cell: &'c Cell<T>) -> Self {
+        CellGuard { value: cell.take(), cell }
+    }
+}
+
+impl<T: Default> Drop for CellGuard<'_, T> {
+    fn drop(&mut self) {
+        self.cell.set(std::mem::take(&mut self.value));
+    }
+}
+
+/// Clears a `computing` flag when the computation it guards is done, or when it panics.
+pub(crate) struct ComputingGuard<'c>(pub(crate) &'c Cell<bool>);
+
+impl Drop for ComputingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::discrete::AddGroupU8;
+    use crate::padic::{raise, PadicAccessor, PadicError, PadicNumber, StaticPadicNumber};
+    use crate::padic_test_util::{digits, fraction, lazy, Counting, Exact, Padic};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn static_numbers_outlive_what_built_them() {
+        fn ones(scale: isize) -> StaticPadicNumber<AddGroupU8<3>> {
+            let one = AddGroupU8::new(1).unwrap();
+            PadicNumber::from_fn(scale, move |_| one)
+        }
+        let saved: Vec<StaticPadicNumber<AddGroupU8<3>>> = (0..3).map(|scale| ones(scale) + ones(0)).collect();
+        // the ones are -1/2, and 9 (-1/2) - 1/2 = -5
+        assert_eq!(digits(&saved[2], 0..8), digits(&PadicNumber::from_exact(Exact::from_integer(-5)), 0..8));
+    }
+
+    #[test]
+    fn exact_arithmetic_stays_exact() {
+        let x = PadicNumber::from_exact(Exact::<3>::from_integer(7));
+        let y = PadicNumber::from_exact(&Exact::<3>::from_integer(-5) / &Exact::from_integer(12));
+        let sum = &x + &y;
+        let quotient = &sum / &x;
+        assert_eq!(sum.exact(), Some(&(&Exact::from_integer(79) / &Exact::from_integer(12))));
+        assert_eq!(quotient.exact(), Some(&(&Exact::from_integer(79) / &Exact::from_integer(84))));
+        assert!((&quotient + &lazy(&Exact::from_integer(0))).exact().is_none());
+        assert!((&lazy(&Exact::from_integer(1)) * &x).exact().is_none());
+    }
+
+    #[test]
+    fn scale_moves_past_every_cancelled_digit() {
+        // 1 + 3^15 and -1 cancel up to digit 15, further than any fixed cutoff on the zeros
+        let x = lazy(&Exact::<3>::from_integer(1 + 3i64.pow(15)));
+        let minus_one = lazy(&Exact::<3>::from_integer(-1));
+        let one = lazy(&Exact::<3>::from_integer(1));
+        let sums = [
+            x.clone() + minus_one.clone(),
+            x.clone() - one.clone(),
+            [x.clone(), minus_one.clone(), one.clone(), minus_one.clone()].into_iter().sum(),
+        ];
+        for sum in sums {
+            assert_eq!(sum.get_scale(), 0);
+            digits(&sum, 0..20);
+            assert_eq!(sum.get_scale(), 15);
+            assert_eq!(sum.valuation(20), Some(15));
+        }
+    }
+
+    #[test]
+    fn exact_numbers_compare_exactly() {
+        assert_eq!((fraction::<5>(1, 3) + fraction(2, 3)).try_eq(&fraction(1, 1)), Some(true));
+        assert_eq!((fraction::<5>(1, 3) * fraction(3, 1)).try_eq(&fraction(1, 1)), Some(true));
+        assert_eq!(fraction::<5>(1, 3).try_eq(&fraction(2, 6).shift(1)), Some(false));
+    }
+
+    #[test]
+    fn comparing_a_lazy_number_is_undecided() {
+        let x = lazy(&Exact::<5>::from_integer(7));
+        assert_eq!(x.try_eq(&x.clone()), Some(true));
+        assert_eq!(x.try_eq(&fraction(7, 1)), None);
+        assert_eq!(fraction(7, 1).try_eq(&x), None);
+        assert_eq!(x.try_eq(&lazy(&Exact::from_integer(7))), None);
+    }
+
+    type BinaryOp = fn(Padic<'static, 5>, Padic<'static, 5>) -> Padic<'static, 5>;
+
+    /// The digits of `x`, except that reading digit `failing` fails the first time.
+    fn failing_once(x: &Exact<5>, failing: isize) -> Padic<'static, 5> {
+        let x = PadicNumber::from_exact(x.clone());
+        let failed = Cell::new(false);
+        PadicNumber::from_fn(x.get_scale(), move |index| {
+            if index == failing && !failed.replace(true) {
+                raise(PadicError::NotConvergent);
+            }
+            x.get_digit(index)
+        })
+    }
+
+    #[test]
+    fn caches_survive_a_caught_error() {
+        let zero = AddGroupU8::new(0).unwrap();
+        let (lhs, rhs) = (&Exact::from_integer(-7) / &Exact::from_integer(3), Exact::from_integer(12));
+        let results: [(BinaryOp, Exact<5>); 5] = [
+            (|a, b| a + b, &lhs + &rhs),
+            (|a, b| a - b, &lhs - &rhs),
+            (|a, b| a * b, &lhs * &rhs),
+            (|a, b| a / b, &lhs / &rhs),
+            // the sum reads its newest term first
+            (|a, b| [b, a].into_iter().sum(), &lhs + &rhs),
+        ];
+        for (i, (op, expected)) in results.into_iter().enumerate() {
+            let expected = PadicNumber::from_exact(expected);
+            for far in [30, 300] {
+                let reads = Rc::new(Cell::new(0));
+                let counting = Counting { inner: PadicNumber::from_exact(rhs.clone()), reads: reads.clone() };
+                let result = op(failing_once(&lhs, 5), counting.to_dyn());
+                assert!(result.try_fill_digits(0..3, &mut [zero; 3]).is_ok());
+                let mut buffer = vec![zero; far as usize];
+                assert_eq!(result.try_fill_digits(0..far, &mut buffer), Err(PadicError::NotConvergent));
+                assert_eq!(digits(&result, 0..far), digits(&expected, 0..far));
+                // the digits read before the error were kept (division reads ahead instead)
+                if i != 3 {
+                    assert_eq!(reads.get(), far as usize);
+                }
+            }
+        }
+        let reciprocal = failing_once(&lhs, 5).reciprocal();
+        assert_eq!(reciprocal.try_fill_digits(0..8, &mut [zero; 8]), Err(PadicError::NotConvergent));
+        let expected = PadicNumber::from_exact(&Exact::one() / &lhs);
+        assert_eq!(digits(&reciprocal, 0..300), digits(&expected, 0..300));
+    }
 }
@@ -1,14 +1,19 @@
 use crate::discrete::Value;
-use crate::padic::{PadicAccessor, PadicIntegerAccessor, PadicNumber, PadicNumberScaler};
+use crate::padic::{
+    fill_scaled_digits, CellGuard, LeadingZeros, PadicAccessor, PadicIntegerAccessor, PadicNumber, PadicNumberScaler,
+};
+use crate::padic_nary::{SumPadicNumber, TermList};
+use crate::padic_periodic::EXACT_DIGIT_LIMIT;
 use std::cell::Cell;
 use std::ops::Add;
+use std::ops::Range;
 use std::rc::Rc;
 
-impl<'a, Digit: Value> Add for &'a PadicNumber<'a, Digit> {
+impl<'a, 'b, Digit: Value + 'a> Add<&'b PadicNumber<'a, Digit>> for &'b PadicNumber<'a, Digit> {
     type Output = PadicNumber<'a, Digit>;
 
     fn add(self, rhs: Self) -> Self::Output {
-        PadicNumber::new_from_rc(Rc::new(AdditivePadicIntegerScaler::new(self.clone(), rhs.clone())))
+        add(self.clone(), rhs.clone())
     }
 }
 
@@ -16,40 +21,74 @@ impl<'a, Digit: Value + 'a> Add for PadicNumber<'a, Digit> {
     type Output = PadicNumber<'a, Digit>;
 
     fn add(self, rhs: Self) -> Self::Output {
-        PadicNumber::new_from_rc(Rc::new(AdditivePadicIntegerScaler::new(self, rhs)))
+        add(self, rhs)
     }
 }
 
+fn add<'a, Digit: Value + 'a>(
+    lhs: PadicNumber<'a, Digit>,
+    rhs: PadicNumber<'a, Digit>,
+) -> PadicNumber<'a, Digit> {
+    if let (Some(lhs), Some(rhs)) = (lhs.exact(), rhs.exact())
+        && let Some(sum) = lhs.checked_add(rhs, EXACT_DIGIT_LIMIT)
+    {
+        return PadicNumber::from_exact(sum);
+    }
+    if !lhs.settle_scale() || !rhs.settle_scale() {
+        return PadicNumber::build_when_read(vec![lhs, rhs], |operands| add(operands[0].clone(), operands[1].clone()));
+    }
+    if let Some(terms) = lhs.sum_terms() {
+        return SumPadicNumber::new(TermList::sum_of(Some(terms), rhs, false)).to_dyn();
+    }
+    if let Some(terms) = rhs.sum_terms() {
+        return SumPadicNumber::new(TermList::sum_of(Some(terms), lhs, false)).to_dyn();
+    }
+    PadicNumber::new_from_rc(Rc::new(AdditivePadicIntegerScaler::new(lhs, rhs)))
+}
+
 pub struct AdditivePadicIntegerScaler<'a, Digit: Value> {
     inner: AdditionPadicInteger<'a, Digit>,
     scale: isize,
-    scale_adjustment: usize,
+    lhs: PadicNumber<'a, Digit>,
+    rhs: PadicNumber<'a, Digit>,
 }
 
 impl<'a, Digit: Value> AdditivePadicIntegerScaler<'a, Digit> {
     pub(crate) fn new(lhs: PadicNumber<'a, Digit>, rhs: PadicNumber<'a, Digit>) -> Self {
         let scale = lhs.get_scale().min(rhs.get_scale());
         let inner = AdditionPadicInteger::new(Rc::new(PadicNumberScaler::new(&lhs, scale)), Rc::new(PadicNumberScaler::new(&rhs, scale)));
-        let mut scale_adjustment = 0;
-        while inner.get_integer_digit(scale_adjustment).is_zero() && scale_adjustment <= 10 {
-            scale_adjustment += 1;
-        }
-        Self { inner, scale, scale_adjustment }
+        Self { inner, scale, lhs, rhs }
     }
 }
 
 impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for AdditivePadicIntegerScaler<'a, Digit> {
     fn get_digit(&self, index: isize) -> Digit {
         let adjusted_index = index - self.scale;
-        if adjusted_index < self.scale_adjustment as isize {
+        if adjusted_index < 0 {
             Digit::zero()
         } else {
             self.inner.get_integer_digit(adjusted_index as usize)
         }
     }
 
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        fill_scaled_digits(&self.inner, self.scale, range, digits);
+    }
+
     fn get_scale(&self) -> isize {
-        self.scale + self.scale_adjustment as isize
+        self.scale + self.inner.leading_zeros.get() as isize
+    }
+
+    fn sum_terms(&self) -> Option<Rc<TermList<'a, Digit>>> {
+        Some(TermList::pair_sum(&self.lhs, &self.rhs, false))
+    }
+
+    fn operands(&self) -> Vec<PadicNumber<'a, Digit>> {
+        vec![self.lhs.clone(), self.rhs.clone()]
+    }
+
+    fn take_operands(&mut self) -> Vec<PadicNumber<'a, Digit>> {
+        vec![self.lhs.take(), self.rhs.take()]
     }
 }
 
@@ -57,6 +96,7 @@ pub(crate) struct AdditionPadicInteger<'a, Digit: Value> {
     lhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
     rhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
     cache: Cell<(Vec<Digit>, bool)>,
+    leading_zeros: LeadingZeros,
 }
 
 impl<'a, Digit: Value> AdditionPadicInteger<'a, Digit> {
@@ -68,27 +108,37 @@ impl<'a, Digit: Value> AdditionPadicInteger<'a, Digit> {
             lhs,
             rhs,
             cache: Cell::new((vec![], false)),
+            leading_zeros: LeadingZeros::default(),
         }
     }
 }
 
 impl<'a, Digit: Value + 'a> PadicIntegerAccessor<'a, Digit> for AdditionPadicInteger<'a, Digit> {
     fn get_integer_digit(&self, index: usize) -> Digit {
-        let (mut digit_cache, mut carry) = self.cache.take();
-
-        let mut digit = *digit_cache.get(index).unwrap_or(&Digit::zero());
-        for i in digit_cache.len()..=index {
-            let lhs_digit = self.lhs.get_integer_digit(i);
-            let rhs_digit = self.rhs.get_integer_digit(i);
-            let (digit_sum, digit_carry) = lhs_digit.add_carry(rhs_digit);
-            let (full_sum, full_carry) = digit_sum.add_carry(Digit::from_bool(carry));
-            digit = full_sum;
-            carry = digit_carry || full_carry;
-            digit_cache.push(digit);
-        }
+        let mut digit = Digit::zero();
+        self.fill_integer_digits(index..index + 1, std::slice::from_mut(&mut digit));
+        digit
+    }
 
-        self.cache.set((digit_cache, carry));
+    fn fill_integer_digits(&self, range: Range<usize>, digits: &mut [Digit]) {
+        let mut cache = CellGuard::take(&self.cache);
+        let (digit_cache, carry) = &mut cache.value;
 
-        digit
+        if digit_cache.len() < range.end {
+            let start = digit_cache.len();
+            let mut lhs = vec![Digit::zero(); range.end - start];
+            let mut rhs = vec![Digit::zero(); range.end - start];
+            self.lhs.fill_integer_digits(start..range.end, &mut lhs);
+            self.rhs.fill_integer_digits(start..range.end, &mut rhs);
+            for (lhs_digit, rhs_digit) in lhs.into_iter().zip(rhs) {
+                let (digit_sum, digit_carry) = lhs_digit.add_carry(rhs_digit);
+                let (full_sum, full_carry) = digit_sum.add_carry(Digit::from_bool(*carry));
+                let digit = full_sum;
+                *carry = digit_carry || full_carry;
+                self.leading_zeros.push(digit_cache.len(), &digit);
+                digit_cache.push(digit);
+            }
+        }
+        digits.copy_from_slice(&digit_cache[range]);
     }
 }
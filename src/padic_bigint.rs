@@ -0,0 +1,123 @@
+use crate::discrete::{AddGroupU8, Zero};
+use crate::padic::{PadicError, PadicNumber};
+use crate::padic_periodic::EventuallyPeriodicPadic;
+use num_bigint::{BigInt, BigUint, Sign};
+use num_rational::BigRational;
+
+fn exact_from_biguint<const BASE: u8>(value: &BigUint) -> EventuallyPeriodicPadic<AddGroupU8<BASE>> {
+    let digits = value.to_radix_le(BASE as u32).into_iter().map(|digit| AddGroupU8::new(digit).unwrap()).collect();
+    EventuallyPeriodicPadic::new(0, digits, vec![])
+}
+
+fn exact_from_bigint<const BASE: u8>(value: &BigInt) -> EventuallyPeriodicPadic<AddGroupU8<BASE>> {
+    let magnitude = exact_from_biguint(value.magnitude());
+    if value.sign() == Sign::Minus { -magnitude } else { magnitude }
+}
+
+impl<'a, const BASE: u8> From<&BigUint> for PadicNumber<'a, AddGroupU8<BASE>> {
+    fn from(value: &BigUint) -> Self {
+        PadicNumber::from_exact(exact_from_biguint(value))
+    }
+}
+
+impl<'a, const BASE: u8> From<BigUint> for PadicNumber<'a, AddGroupU8<BASE>> {
+    fn from(value: BigUint) -> Self {
+        Self::from(&value)
+    }
+}
+
+impl<'a, const BASE: u8> From<&BigInt> for PadicNumber<'a, AddGroupU8<BASE>> {
+    fn from(value: &BigInt) -> Self {
+        PadicNumber::from_exact(exact_from_bigint(value))
+    }
+}
+
+impl<'a, const BASE: u8> From<BigInt> for PadicNumber<'a, AddGroupU8<BASE>> {
+    fn from(value: BigInt) -> Self {
+        Self::from(&value)
+    }
+}
+
+/// Stays exact unless the period of the quotient is too long, the same as dividing two numbers.
+impl<'a, const BASE: u8> From<&BigRational> for PadicNumber<'a, AddGroupU8<BASE>> {
+    fn from(value: &BigRational) -> Self {
+        Self::from(value.numer()) / Self::from(value.denom())
+    }
+}
+
+impl<'a, const BASE: u8> From<BigRational> for PadicNumber<'a, AddGroupU8<BASE>> {
+    fn from(value: BigRational) -> Self {
+        Self::from(&value)
+    }
+}
+
+impl<'a, const BASE: u8> PadicNumber<'a, AddGroupU8<BASE>> {
+    /// `self` mod p^n, which is digits 0 to n - 1. Fails if a digit after the point isn't zero.
+    pub fn to_biguint_residue(&self, n: usize) -> Result<BigUint, PadicError> {
+        if (self.get_scale()..0).any(|i| !self.get_digit(i).is_zero()) {
+            return Err(PadicError::NotAnInteger);
+        }
+        let mut digits = vec![AddGroupU8::zero(); n];
+        self.fill_digits(0..n as isize, &mut digits);
+        let digits: Vec<u8> = digits.into_iter().map(AddGroupU8::value).collect();
+        Ok(BigUint::from_radix_le(&digits, BASE as u32).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::padic_test_util::Padic;
+
+    /// `value` mod p^n, as a non-negative number.
+    fn residue(value: &BigInt, n: usize) -> BigUint {
+        let modulus = BigInt::from(3u8).pow(n as u32);
+        (((value % &modulus) + &modulus) % &modulus).to_biguint().unwrap()
+    }
+
+    fn big_values() -> Vec<BigInt> {
+        let above_64_bits = BigInt::from(3u8).pow(50) + BigInt::from(7);
+        vec![
+            BigInt::from(0),
+            BigInt::from(5),
+            BigInt::from(-5),
+            BigInt::from(u64::MAX) * 1000,
+            above_64_bits.clone(),
+            -above_64_bits,
+        ]
+    }
+
+    #[test]
+    fn bigints_round_trip_through_residues() {
+        for value in big_values() {
+            let x: Padic<3> = Padic::from(&value);
+            for n in [0, 1, 10, 80] {
+                assert_eq!(x.to_biguint_residue(n), Ok(residue(&value, n)), "{value} mod 3^{n}");
+            }
+        }
+    }
+
+    #[test]
+    fn biguints_round_trip_through_residues() {
+        for value in big_values().into_iter().filter_map(|value| value.to_biguint()) {
+            let x: Padic<3> = Padic::from(value.clone());
+            assert_eq!(x.to_biguint_residue(80), Ok(value));
+        }
+    }
+
+    #[test]
+    fn rationals_with_a_unit_denominator_have_residues() {
+        let x: Padic<3> = Padic::from(BigRational::new(BigInt::from(-7), BigInt::from(2) << 70));
+        let x_mod = x.to_biguint_residue(40).unwrap();
+        assert_eq!(x_mod * (BigUint::from(2u8) << 70) % BigUint::from(3u8).pow(40), residue(&BigInt::from(-7), 40));
+    }
+
+    #[test]
+    fn non_integers_have_no_residue() {
+        let x: Padic<3> = Padic::from(BigRational::new(BigInt::from(4), BigInt::from(9)));
+        assert_eq!(x.to_biguint_residue(5), Err(PadicError::NotAnInteger));
+        // 9/3 only looks like a fraction
+        let x: Padic<3> = Padic::from(BigRational::new_raw(BigInt::from(9), BigInt::from(3)));
+        assert_eq!(x.to_biguint_residue(5), Ok(BigUint::from(3u8)));
+    }
+}
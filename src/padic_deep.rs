@@ -0,0 +1,255 @@
+//! Reading and dropping numbers built from deeply nested operations.
+//!
+//! Nodes read their operands recursively, so a number built by a loop like `acc = acc * x + 1`
+//! or `acc = acc / y` needs a stack frame per step to read a digit, and another to be dropped.
+//! Flattening sums and products (see [`TermList`]) only helps when every step is the same
+//! operation, so the graph is also walked with explicit work lists here:
+//!
+//! - Operand reads through [`PadicNumberScaler`] count how deeply they are nested. Past
+//!   [`MAX_NESTING`], a read of an operand that is itself deep stops and hands the operand and
+//!   the digits it needs to the outermost [`PadicNumber::get_digit`] or
+//!   [`PadicNumber::fill_digits`]. That computes them first, from the top of the stack, and then
+//!   starts over. The operand has cached them by then, so the next attempt gets further, and so
+//!   on down the graph. Every attempt computes a digit all the way down, so deep numbers are best
+//!   read a block of digits at a time.
+//! - Dropping a number that nothing else refers to moves the operands out of its node (see
+//!   [`PadicAccessor::take_operands`]) onto a list instead of dropping them recursively.
+//!
+//! Both only see the operands a node lists with [`PadicAccessor::operands`]. A read that stops at
+//! an operand that isn't listed, like one a closure reads, makes the outermost read give up on
+//! this and recurse as before.
+//!
+//! [`TermList`]: crate::padic_nary::TermList
+//! [`PadicNumberScaler`]: crate::padic::PadicNumberScaler
+//! [`PadicAccessor::operands`]: crate::padic::PadicAccessor::operands
+//! [`PadicAccessor::take_operands`]: crate::padic::PadicAccessor::take_operands
+
+use crate::discrete::Value;
+use crate::padic::PadicNumber;
+use crate::padic_periodic::EventuallyPeriodicPadic;
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::{Entry, HashMap};
+use std::ops::Range;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::rc::Rc;
+
+/// How many operand reads can be nested before the innermost one is handed to the outermost
+/// read instead. Each costs a handful of stack frames, so this stays well within the 2 MiB stack
+/// of a test thread.
+pub const MAX_NESTING: usize = 100;
+
+thread_local! {
+    /// How many operand reads are running on this thread.
+    static NESTING: Cell<usize> = const { Cell::new(0) };
+    /// Whether an outermost read is running that can take over a read nested too deeply.
+    static DEFERRING: Cell<bool> = const { Cell::new(false) };
+    /// The node a read nested too deeply stopped at, and the digits it needed from it.
+    static DEFERRED: Cell<Option<(usize, Range<isize>)>> = const { Cell::new(None) };
+    /// What the outermost read knows about the nodes it reads, by their address, once a read has
+    /// been nested too deeply.
+    static NODES: RefCell<HashMap<usize, Node>> = RefCell::new(HashMap::new());
+}
+
+struct Node {
+    /// How many reads of operands reading a digit of the node can be nested, at most.
+    depth: usize,
+    /// The end of the digits the outermost read has computed so far. Nodes cache every digit up
+    /// to the last one they computed, so reading any digit below this doesn't go any deeper.
+    computed: isize,
+}
+
+/// The payload a read nested too deeply unwinds with, up to the outermost read.
+struct Deferred;
+
+/// Sets a thread-local back to what it was when dropped, unwinding or not.
+struct Restore<T: Copy + 'static>(&'static std::thread::LocalKey<Cell<T>>, T);
+
+impl<T: Copy + 'static> Restore<T> {
+    fn set(key: &'static std::thread::LocalKey<Cell<T>>, value: T) -> Self {
+        Restore(key, key.replace(value))
+    }
+}
+
+impl<T: Copy + 'static> Drop for Restore<T> {
+    fn drop(&mut self) {
+        self.0.set(self.1);
+    }
+}
+
+fn address<'a, Digit: Value + 'a>(number: &PadicNumber<'a, Digit>) -> usize {
+    Rc::as_ptr(&number.value) as *const () as usize
+}
+
+/// Runs `read`, a read of the digits `range` of `operand` from inside another node, unless it's
+/// nested too deeply, in which case the outermost read is asked to compute them first.
+pub(crate) fn read_operand<'a, Digit: Value + 'a, T>(
+    operand: &PadicNumber<'a, Digit>,
+    range: Range<isize>,
+    read: impl FnOnce() -> T,
+) -> T {
+    let nesting = NESTING.get();
+    if nesting >= MAX_NESTING && DEFERRING.get() {
+        let address = address(operand);
+        // until the outermost read has looked at the graph, every node could be deep
+        let deep = NODES.with_borrow(|nodes| match nodes.get(&address) {
+            Some(node) => {
+                node.depth >= MAX_NESTING && node.computed < range.end
+            }
+            None => nodes.is_empty(),
+        });
+        if deep {
+            DEFERRED.set(Some((address, range)));
+            resume_unwind(Box::new(Deferred));
+        }
+    }
+    let _nesting = Restore::set(&NESTING, nesting + 1);
+    read()
+}
+
+/// Runs `read`, a read of the digits of `number`, computing whatever operands it finds nested too
+/// deeply first (see the [module documentation](self)).
+///
+/// `read` can run more than once, so it must not have side effects beyond the caches of the
+/// nodes it reads.
+pub(crate) fn read_outermost<'a, Digit: Value + 'a, T>(number: &PadicNumber<'a, Digit>, mut read: impl FnMut() -> T) -> T {
+    if DEFERRING.get() || NESTING.get() > 0 {
+        return read();
+    }
+    let _deferring = Restore::set(&DEFERRING, true);
+    struct Forget;
+    impl Drop for Forget {
+        fn drop(&mut self) {
+            NODES.with_borrow_mut(HashMap::clear);
+        }
+    }
+    let _forget = Forget;
+    let mut pending: Vec<(PadicNumber<'a, Digit>, Range<isize>)> = Vec::new();
+    // keeps every node alive while the addresses in `NODES` refer to them
+    let mut nodes = None;
+    loop {
+        let attempt = catch_unwind(AssertUnwindSafe(|| match pending.last() {
+            Some((operand, range)) => {
+                let mut digits = vec![Digit::zero(); range.len()];
+                operand.value.fill_digits(range.clone(), &mut digits);
+                None
+            }
+            None => Some(read()),
+        }));
+        match attempt {
+            Ok(Some(result)) => return result,
+            Ok(None) => {
+                let (operand, range) = pending.pop().expect("only pending reads return nothing");
+                NODES.with_borrow_mut(|nodes| {
+                    let node = nodes.get_mut(&address(&operand)).expect("pending reads are of known nodes");
+                    node.computed = node.computed.max(range.end);
+                });
+            }
+            Err(payload) if payload.is::<Deferred>() => {
+                let (operand, range) = DEFERRED.take().expect("a deferred read records its operand");
+                match nodes.get_or_insert_with(|| graph(number)).get(&operand) {
+                    Some(operand) => pending.push((PadicNumber::clone(operand), range)),
+                    // only a closure can read this operand, so there's no telling where it is
+                    None => DEFERRING.set(false),
+                }
+            }
+            Err(payload) => resume_unwind(payload),
+        }
+    }
+}
+
+/// Every number `number` is built from, by the address of its node, with their depths put into
+/// `NODES`.
+fn graph<'a, Digit: Value + 'a>(number: &PadicNumber<'a, Digit>) -> HashMap<usize, PadicNumber<'a, Digit>> {
+    let mut nodes = HashMap::new();
+    let mut depths = HashMap::new();
+    // each node is visited twice: first to push its operands, then, once they have their
+    // depths, to work out its own
+    let mut stack = vec![(number.clone(), false)];
+    while let Some((node, operands_done)) = stack.pop() {
+        let node_address = address(&node);
+        if operands_done {
+            let operands = node.value.operands();
+            let depth = operands.iter().map(|operand| depths.get(&address(operand)).map_or(0, |depth| depth + 1)).max();
+            depths.insert(node_address, depth.unwrap_or(0));
+        } else if let Entry::Vacant(entry) = nodes.entry(node_address) {
+            let operands = node.value.operands();
+            entry.insert(node.clone());
+            stack.push((node, true));
+            stack.extend(operands.into_iter().map(|operand| (operand, false)));
+        }
+    }
+    NODES.with_borrow_mut(|known| {
+        known.extend(depths.into_iter().map(|(address, depth)| (address, Node { depth, computed: isize::MIN })));
+    });
+    nodes
+}
+
+impl<'a, Digit: Value + 'a> PadicNumber<'a, Digit> {
+    /// Moves this number out, leaving an exact zero, for
+    /// [`PadicAccessor::take_operands`](crate::padic::PadicAccessor::take_operands).
+    pub(crate) fn take(&mut self) -> PadicNumber<'a, Digit> {
+        std::mem::replace(self, PadicNumber::from_exact(EventuallyPeriodicPadic::zero()))
+    }
+}
+
+/// Drops the operands of a node nothing else refers to through a work list, so dropping a deep
+/// graph doesn't take a stack frame per node.
+impl<'a, Digit: Value> Drop for PadicNumber<'a, Digit> {
+    fn drop(&mut self) {
+        let mut operands = take_operands(self);
+        while let Some(mut operand) = operands.pop() {
+            operands.append(&mut take_operands(&mut operand));
+        }
+    }
+}
+
+/// Moves the operands out of the node of `number` if nothing else refers to it, and drops the
+/// node right away, which drops the references its operand readers hold to them.
+fn take_operands<'a, Digit: Value>(number: &mut PadicNumber<'a, Digit>) -> Vec<PadicNumber<'a, Digit>> {
+    let operands = match Rc::get_mut(&mut number.value) {
+        Some(node) => node.take_operands(),
+        None => return vec![],
+    };
+    if !operands.is_empty() {
+        number.value = Rc::new(EventuallyPeriodicPadic::zero());
+    }
+    operands
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::discrete::{AddGroupU8, Zero};
+    use crate::padic::PadicNumber;
+    use crate::padic_test_util::{digits, lazy, Exact};
+
+    #[test]
+    fn horner_loops_read_and_drop_without_recursing() {
+        // 1 - 1 + 1 - ... with 100,001 terms
+        let x = lazy(&Exact::<3>::from_integer(-1));
+        let one = PadicNumber::from_exact(Exact::one());
+        let mut acc = lazy(&Exact::one());
+        for _ in 0..100_000 {
+            acc = &(&acc * &x) + &one;
+        }
+        assert!(acc.exact().is_none());
+        // in one block, as reading digit by digit goes through the whole graph for every digit
+        let mut block = vec![AddGroupU8::zero(); 20];
+        acc.fill_digits(-4..16, &mut block);
+        assert_eq!(block, digits(&one, -4..16));
+    }
+
+    #[test]
+    fn division_chains_read_and_drop_without_recursing() {
+        let y = lazy(&Exact::<3>::from_integer(-1));
+        let two = PadicNumber::from_exact(Exact::from_integer(2));
+        let mut acc = lazy(&Exact::from_integer(2));
+        for _ in 0..100_000 {
+            acc = &acc / &y;
+        }
+        assert!(acc.exact().is_none());
+        // in one block, as reading digit by digit goes through the whole graph for every digit
+        let mut block = vec![AddGroupU8::zero(); 20];
+        acc.fill_digits(-4..16, &mut block);
+        assert_eq!(block, digits(&two, -4..16));
+    }
+}
@@ -1,20 +1,20 @@
+use crate::digit_vec;
 use crate::discrete::Value;
 use crate::padic::{
-    PadicAccessor, PadicIntegerAccessor, PadicIntegerToNumber, PadicNumber, PadicNumberScaler,
+    checked_index, raise, CellGuard, LeadingZeros, PadicAccessor, PadicError, PadicIntegerAccessor, PadicNumber, PadicNumberScaler,
 };
-use crate::padic_primitive::FinitePadicInteger;
+use crate::padic_mul::add_vec;
+use crate::padic_periodic::{EventuallyPeriodicPadic, EXACT_DIGIT_LIMIT};
 use std::cell::Cell;
-use std::ops::Div;
+use std::collections::VecDeque;
+use std::ops::{Div, Range};
 use std::rc::Rc;
 
-impl<'a, Digit: Value + 'a> Div for &'a PadicNumber<'a, Digit> {
+impl<'a, 'b, Digit: Value + 'a> Div<&'b PadicNumber<'a, Digit>> for &'b PadicNumber<'a, Digit> {
     type Output = PadicNumber<'a, Digit>;
 
     fn div(self, rhs: Self) -> Self::Output {
-        PadicNumber::new_from_rc(Rc::new(DivisivePadicIntegerScaler::new(
-            self.clone(),
-            rhs.clone(),
-        )))
+        div(self.clone(), rhs.clone())
     }
 }
 
@@ -22,23 +22,43 @@ impl<'a, Digit: Value + 'a> Div for PadicNumber<'a, Digit> {
     type Output = PadicNumber<'a, Digit>;
 
     fn div(self, rhs: Self) -> Self::Output {
-        PadicNumber::new_from_rc(Rc::new(DivisivePadicIntegerScaler::new(self, rhs)))
+        div(self, rhs)
     }
 }
 
+fn div<'a, Digit: Value + 'a>(
+    lhs: PadicNumber<'a, Digit>,
+    rhs: PadicNumber<'a, Digit>,
+) -> PadicNumber<'a, Digit> {
+    if rhs.exact().is_some_and(EventuallyPeriodicPadic::is_zero) {
+        raise(PadicError::DivisionByZero);
+    }
+    if let (Some(lhs), Some(rhs)) = (lhs.exact(), rhs.exact())
+        && let Some(quotient) = lhs.checked_div(rhs, EXACT_DIGIT_LIMIT)
+    {
+        return PadicNumber::from_exact(quotient);
+    }
+    if !lhs.settle_scale() || !rhs.settle_scale() {
+        return PadicNumber::build_when_read(vec![lhs, rhs], |operands| div(operands[0].clone(), operands[1].clone()));
+    }
+    PadicNumber::new_from_rc(Rc::new(DivisivePadicIntegerScaler::new(lhs, rhs)))
+}
+
 pub struct DivisivePadicIntegerScaler<'a, Digit: Value> {
     inner: DivisionPadicInteger<'a, Digit>,
     scale: isize,
+    lhs: PadicNumber<'a, Digit>,
+    rhs: PadicNumber<'a, Digit>,
 }
 
 impl<'a, Digit: Value> DivisivePadicIntegerScaler<'a, Digit> {
     fn new(lhs: PadicNumber<'a, Digit>, rhs: PadicNumber<'a, Digit>) -> Self {
-        let scale = lhs.get_scale() - rhs.get_scale();
+        let scale = checked_index(lhs.get_scale().checked_sub(rhs.get_scale()));
         let inner = DivisionPadicInteger::new(
             Rc::new(PadicNumberScaler::new(&lhs, lhs.get_scale())),
             Rc::new(PadicNumberScaler::new(&rhs, rhs.get_scale())),
         );
-        DivisivePadicIntegerScaler { inner, scale }
+        DivisivePadicIntegerScaler { inner, scale, lhs, rhs }
     }
 }
 
@@ -47,21 +67,223 @@ impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for DivisivePadicIntegerSca
         self.inner.get_digit(index - self.scale)
     }
 
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        self.inner.fill_digits(range.start - self.scale..range.end - self.scale, digits);
+    }
+
     fn get_scale(&self) -> isize {
         self.scale + self.inner.get_scale()
     }
+
+    fn settle_scale(&self) -> bool {
+        self.inner.reciprocal.try_scale_adjustment().is_some()
+    }
+
+    fn operands(&self) -> Vec<PadicNumber<'a, Digit>> {
+        vec![self.lhs.clone(), self.rhs.clone()]
+    }
+
+    fn take_operands(&mut self) -> Vec<PadicNumber<'a, Digit>> {
+        vec![self.lhs.take(), self.rhs.take()]
+    }
 }
 
-pub(crate) struct DivisionPadicInteger<'a, Digit: Value> {
+impl<'a, Digit: Value + 'a> PadicNumber<'a, Digit> {
+    /// Returns `1 / self`, computed with Newton's iteration when it isn't exact.
+    ///
+    /// Panics with [`PadicError::DivisionByZero`] when `self` is an exact zero. A lazy zero can't
+    /// be told apart from a number with a lot of low zero digits, so reading its reciprocal never
+    /// finishes.
+    pub fn reciprocal(&self) -> PadicNumber<'a, Digit> {
+        if self.exact().is_some_and(EventuallyPeriodicPadic::is_zero) {
+            raise(PadicError::DivisionByZero);
+        }
+        if let Some(exact) = self.exact()
+            && let Some(reciprocal) = EventuallyPeriodicPadic::one().checked_div(exact, EXACT_DIGIT_LIMIT)
+        {
+            return PadicNumber::from_exact(reciprocal);
+        }
+        if !self.settle_scale() {
+            return PadicNumber::build_when_read(vec![self.clone()], |operands| operands[0].reciprocal());
+        }
+        PadicNumber::new_from_rc(Rc::new(ReciprocalPadicNumber::new(self.clone())))
+    }
+}
+
+pub struct ReciprocalPadicNumber<'a, Digit: Value> {
+    inner: ReciprocalPadicInteger<'a, Digit>,
+    scale: isize,
+    value: PadicNumber<'a, Digit>,
+}
+
+impl<'a, Digit: Value> ReciprocalPadicNumber<'a, Digit> {
+    fn new(value: PadicNumber<'a, Digit>) -> Self {
+        let inner = ReciprocalPadicInteger::new(Rc::new(PadicNumberScaler::new(&value, value.get_scale())));
+        ReciprocalPadicNumber { inner, scale: -value.get_scale(), value }
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for ReciprocalPadicNumber<'a, Digit> {
+    fn get_digit(&self, index: isize) -> Digit {
+        self.inner.get_digit(index - self.scale)
+    }
+
+    fn get_scale(&self) -> isize {
+        self.scale + self.inner.get_scale()
+    }
+
+    fn settle_scale(&self) -> bool {
+        self.inner.try_scale_adjustment().is_some()
+    }
+
+    fn operands(&self) -> Vec<PadicNumber<'a, Digit>> {
+        vec![self.value.clone()]
+    }
+
+    fn take_operands(&mut self) -> Vec<PadicNumber<'a, Digit>> {
+        vec![self.value.take()]
+    }
+}
+
+/// How many more low zeros of a divisor [`PadicAccessor::settle_scale`] counts before it gives
+/// up, leaving arithmetic on the quotient to wait until it's read.
+const SETTLING_ZEROS: usize = 32;
+
+/// Counts the zero digits at the bottom of a divisor, which never finishes if it's a lazy zero.
+pub(crate) fn count_low_zeros<'a, Digit: Value + 'a>(rhs: &dyn PadicIntegerAccessor<'a, Digit>) -> usize {
+    let mut zeros = 0;
+    while rhs.get_integer_digit(zeros).is_zero() {
+        zeros += 1;
+    }
+    zeros
+}
+
+/// The reciprocal of a p-adic integer by Newton's iteration `x <- x (2 - a x)`.
+///
+/// Every step doubles the number of correct digits using two block multiplications, so getting
+/// `n` digits costs a small multiple of one `n`-digit product, but it reads up to twice as many
+/// digits of `rhs` as it returns. The trailing zeros of `rhs` are only counted once a digit or
+/// the scale is asked for, so building the reciprocal of a lazy zero doesn't hang, and building on
+/// it only counts a few of them (see [`PadicAccessor::settle_scale`]).
+pub(crate) struct ReciprocalPadicInteger<'a, Digit: Value> {
     rhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
-    scale_adjustment: usize,
-    cache: Cell<
-        Option<(
-            Vec<Digit>,
-            Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
-            usize,
-        )>,
-    >,
+    scale_adjustment: Cell<Option<usize>>,
+    /// How many trailing zeros of `rhs` have been counted so far, until they all are.
+    zeros_seen: LeadingZeros,
+    /// The digits of `rhs` read so far, with its trailing zeros dropped.
+    unit_digits: Cell<Vec<Digit>>,
+    cache: Cell<Vec<Digit>>,
+}
+
+impl<'a, Digit: Value> ReciprocalPadicInteger<'a, Digit> {
+    pub(crate) fn new(rhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>) -> ReciprocalPadicInteger<'a, Digit> {
+        ReciprocalPadicInteger {
+            rhs,
+            scale_adjustment: Cell::new(None),
+            zeros_seen: LeadingZeros::default(),
+            unit_digits: Cell::new(vec![]),
+            cache: Cell::new(vec![]),
+        }
+    }
+
+    /// The number of trailing zeros of `rhs`.
+    fn scale_adjustment(&self) -> usize {
+        loop {
+            if let Some(zeros) = self.try_scale_adjustment() {
+                return zeros;
+            }
+        }
+    }
+
+    /// The number of trailing zeros of `rhs`, if there are at most [`SETTLING_ZEROS`] more than
+    /// counted before.
+    fn try_scale_adjustment(&self) -> Option<usize> {
+        if let Some(zeros) = self.scale_adjustment.get() {
+            return Some(zeros);
+        }
+        let start = self.zeros_seen.get();
+        for index in start..start + SETTLING_ZEROS {
+            let digit = self.rhs.get_integer_digit(index);
+            if !digit.is_zero() {
+                self.scale_adjustment.set(Some(index));
+                return Some(index);
+            }
+            self.zeros_seen.push(index, &digit);
+        }
+        None
+    }
+
+    /// The digits of `rhs` with its trailing zeros dropped, so digit 0 is invertible, at least
+    /// `len` of them. Each digit is only read once.
+    fn unit_digits(&self, len: usize) -> CellGuard<'_, Vec<Digit>> {
+        let zeros = self.scale_adjustment();
+        let mut unit_digits = CellGuard::take(&self.unit_digits);
+        let start = unit_digits.value.len();
+        if start < len {
+            let mut digits = vec![Digit::zero(); len - start];
+            self.rhs.fill_integer_digits(start + zeros..len + zeros, &mut digits);
+            unit_digits.value.extend(digits);
+        }
+        unit_digits
+    }
+
+    /// The lowest `len` digits of the reciprocal of `rhs` with its trailing zeros dropped.
+    pub(crate) fn unit_reciprocal(&self, len: usize) -> Vec<Digit> {
+        let mut cache = CellGuard::take(&self.cache);
+        let reciprocal = &mut cache.value;
+        if reciprocal.is_empty() {
+            reciprocal.push(self.unit_digits(1).value[0].inverse());
+        }
+        while reciprocal.len() < len {
+            self.newton_step(reciprocal);
+        }
+        reciprocal[..len].to_vec()
+    }
+
+    /// Takes `x`, the reciprocal mod `p^n`, to the reciprocal mod `p^2n`.
+    fn newton_step(&self, x: &mut Vec<Digit>) {
+        let n = x.len();
+        let rhs = self.unit_digits(2 * n);
+        // rhs * x = 1 + p^n h (mod p^2n), and x (2 - rhs x) = x - p^n (x h)
+        let product = digit_vec::mul(x, &rhs.value[..2 * n]);
+        let high: Vec<Digit> = (n..2 * n).map(|i| product.get(i).copied().unwrap_or(Digit::zero())).collect();
+        let correction = digit_vec::mul(x, &high);
+        let mut borrow = false;
+        for i in 0..n {
+            let correction_digit = correction.get(i).copied().unwrap_or(Digit::zero());
+            let (digit_difference, digit_borrow) = Digit::zero().sub_borrow(correction_digit);
+            let (full_difference, full_borrow) = digit_difference.sub_borrow(Digit::from_bool(borrow));
+            x.push(full_difference);
+            borrow = digit_borrow || full_borrow;
+        }
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for ReciprocalPadicInteger<'a, Digit> {
+    fn get_digit(&self, index: isize) -> Digit {
+        let Ok(adjusted_index) = usize::try_from(index + self.scale_adjustment() as isize) else {
+            return Digit::zero();
+        };
+        self.unit_reciprocal(adjusted_index + 1)[adjusted_index]
+    }
+
+    fn get_scale(&self) -> isize {
+        -(self.scale_adjustment() as isize)
+    }
+}
+
+/// Schoolbook division from the lowest digit up. Quotient digit `i` is picked so that column `i`
+/// of `quotient * rhs` matches `lhs`, and `carry` holds what the columns so far carry into the
+/// next one, so memory only grows with the number of digits computed. The digits of `rhs` are
+/// kept by the reciprocal, so both ways read each of them once.
+///
+/// For long requests it switches to multiplying `lhs` by the Newton [`ReciprocalPadicInteger`]
+/// of `rhs`, once the estimated number of digit multiplications says that is cheaper. After that
+/// the carry isn't kept up to date anymore, so later requests double the precision the same way.
+pub(crate) struct DivisionPadicInteger<'a, Digit: Value> {
+    lhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
+    reciprocal: ReciprocalPadicInteger<'a, Digit>,
+    cache: Cell<(Vec<Digit>, Option<VecDeque<Digit>>)>,
 }
 
 impl<'a, Digit: Value> DivisionPadicInteger<'a, Digit> {
@@ -70,72 +292,190 @@ impl<'a, Digit: Value> DivisionPadicInteger<'a, Digit> {
         rhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
     ) -> DivisionPadicInteger<'a, Digit> {
         DivisionPadicInteger {
-            rhs: rhs.clone(),
-            scale_adjustment: {
-                let mut scale_adjustment = 0;
-                while rhs.get_integer_digit(scale_adjustment).is_zero() {
-                    scale_adjustment += 1;
-                    if scale_adjustment.is_multiple_of(100) {
-                        println!(
-                            "Might be dividing by zero! {} zeros so far...",
-                            scale_adjustment,
-                        );
-                    }
-                }
-                scale_adjustment
-            },
-
-            cache: Cell::new(Some((vec![], lhs, 0))),
+            lhs,
+            reciprocal: ReciprocalPadicInteger::new(rhs),
+            cache: Cell::new((vec![], Some(VecDeque::new()))),
         }
     }
+
+    fn schoolbook_cost(from: usize, to: usize) -> usize {
+        (to * to - from * from) / 2
+    }
+
+    // One product for the quotient, plus about three for the Newton steps of the reciprocal, but
+    // a schoolbook step pushes every digit product through the carry `VecDeque`, which makes it
+    // about 15 times slower per product. Timing digit-by-digit reads of quotients of 7-adic
+    // numbers up to 8192 digits (release build), weights from 1/4 down were the fastest
+    // everywhere; 1/2 is as fast from 1024 digits on (64 ms for 8192 digits, against 1.3 s with a
+    // weight of 4) and keeps the schoolbook path, which doesn't read ahead, below 64 digits.
+    fn newton_cost(len: usize) -> usize {
+        digit_vec::mul_cost(len) / 2
+    }
 }
 
 impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for DivisionPadicInteger<'a, Digit> {
     fn get_digit(&self, index: isize) -> Digit {
-        let (mut digit_cache, mut remaining, mut remaining_offset) = self.cache.take().unwrap();
-
-        let result = match usize::try_from(index + self.scale_adjustment as isize) {
-            Ok(adjusted_index) => {
-                if adjusted_index < digit_cache.len() {
-                    digit_cache[adjusted_index]
-                } else {
-                    for index in digit_cache.len()..=adjusted_index {
-                        let next_remaining_digit = remaining.get_integer_digit(remaining_offset);
-                        let d = if next_remaining_digit.is_zero() {
-                            Digit::zero()
-                        } else {
-                            next_remaining_digit
-                                * self.rhs.get_integer_digit(self.scale_adjustment).inverse()
-                        };
-                        if !d.is_zero() {
-                            remaining = Rc::new(PadicNumberScaler::new(
-                                &(PadicIntegerToNumber::new(remaining, 0).to_dyn()
-                                    - (FinitePadicInteger::new_with_digits({
-                                        let mut v = vec![Digit::zero(); index + 1];
-                                        v[index] = d;
-                                        v
-                                    })
-                                    .to_dyn()
-                                        * PadicIntegerToNumber::new(self.rhs.clone(), self.scale_adjustment as isize).to_dyn())),
-                                0,
-                            ));
+        let Ok(adjusted_index) = usize::try_from(index + self.reciprocal.scale_adjustment() as isize) else {
+            return Digit::zero();
+        };
+
+        let mut cache = CellGuard::take(&self.cache);
+        let (digit_cache, carry) = &mut cache.value;
+
+        if adjusted_index >= digit_cache.len() {
+            let newton_len = (adjusted_index + 1).max(2 * digit_cache.len());
+            match carry {
+                Some(carry)
+                    if Self::schoolbook_cost(digit_cache.len(), newton_len) <= Self::newton_cost(newton_len) =>
+                {
+                    // every digit is read before the cache changes, so an error reading one
+                    // leaves it as it was
+                    let start = digit_cache.len();
+                    let rhs = self.reciprocal.unit_digits(adjusted_index + 1);
+                    let rhs = &rhs.value;
+                    let mut lhs = vec![Digit::zero(); adjusted_index + 1 - start];
+                    self.lhs.fill_integer_digits(start..adjusted_index + 1, &mut lhs);
+                    let rhs_inverse = rhs[0].inverse();
+                    for (index, lhs_digit) in (start..=adjusted_index).zip(lhs) {
+                        for (offset, quotient_digit) in digit_cache.iter().enumerate() {
+                            let (low, high) = quotient_digit.mul_overflow(rhs[index - offset]);
+                            *carry = add_vec(std::mem::take(carry), VecDeque::from(vec![low, high]));
                         }
-                        remaining_offset += 1;
+                        let low_digit = carry.front().copied().unwrap_or(Digit::zero());
+                        let d = lhs_digit.sub_borrow(low_digit).0 * rhs_inverse;
+                        let (low, high) = d.mul_overflow(rhs[0]);
+                        *carry = add_vec(std::mem::take(carry), VecDeque::from(vec![low, high]));
+                        // the lowest digit now matches `lhs_digit`, and what's left carries over
+                        carry.pop_front();
                         digit_cache.push(d);
                     }
-                    digit_cache[adjusted_index]
+                }
+                _ => {
+                    let mut lhs = vec![Digit::zero(); newton_len];
+                    self.lhs.fill_integer_digits(0..newton_len, &mut lhs);
+                    let quotient = digit_vec::mul(&lhs, &self.reciprocal.unit_reciprocal(newton_len));
+                    *digit_cache = (0..newton_len).map(|i| quotient.get(i).copied().unwrap_or(Digit::zero())).collect();
+                    *carry = None;
                 }
             }
-            Err(..) => Digit::zero(),
-        };
-
-        self.cache
-            .set(Some((digit_cache, remaining, remaining_offset)));
+        }
+        digit_cache[adjusted_index]
+    }
 
-        result
+    /// Computes the last digit first, which reads the operands a block at a time.
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        if let Some(last) = range.clone().last() {
+            self.get_digit(last);
+        }
+        for (digit, index) in digits.iter_mut().zip(range) {
+            *digit = self.get_digit(index);
+        }
     }
 
     fn get_scale(&self) -> isize {
-        -(self.scale_adjustment as isize)
+        -(self.reciprocal.scale_adjustment() as isize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::padic::PadicNumber;
+    use crate::padic::PadicAccessor;
+    use crate::padic_test_util::{digits, lazy, operands, Counting, Exact, Padic};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn check_quotients<const BASE: u8>(far: isize, jump: bool) {
+        for lhs in operands::<BASE>() {
+            for rhs in operands::<BASE>() {
+                let quotient = lazy(&lhs) / lazy(&rhs);
+                assert!(quotient.exact().is_none());
+                let expected = PadicNumber::from_exact(&lhs / &rhs);
+                // jumping ahead takes the Newton path right away, while reading in order starts
+                // out digit by digit
+                if jump {
+                    quotient.get_digit(far);
+                }
+                assert_eq!(digits(&quotient, -8..far), digits(&expected, -8..far));
+            }
+        }
+    }
+
+    fn check_reciprocals<const BASE: u8>(far: isize) {
+        for value in operands::<BASE>() {
+            let reciprocal = lazy(&value).reciprocal();
+            assert!(reciprocal.exact().is_none());
+            let expected = PadicNumber::from_exact(&Exact::one() / &value);
+            assert_eq!(reciprocal.get_scale(), expected.get_scale());
+            assert_eq!(digits(&reciprocal, -8..far), digits(&expected, -8..far));
+        }
+    }
+
+    #[test]
+    fn quotients_read_in_order_agree_with_exact_division() {
+        check_quotients::<2>(300, false);
+        check_quotients::<3>(300, false);
+        check_quotients::<7>(300, false);
+    }
+
+    #[test]
+    fn long_quotients_agree_with_exact_division() {
+        check_quotients::<2>(1500, true);
+        check_quotients::<3>(1500, true);
+        check_quotients::<7>(1500, true);
+    }
+
+    #[test]
+    fn reciprocals_agree_with_exact_division() {
+        check_reciprocals::<2>(1000);
+        check_reciprocals::<5>(1000);
+    }
+
+    #[test]
+    fn reading_in_order_reads_every_divisor_digit_once() {
+        let reads = Rc::new(Cell::new(0));
+        let divisor = Counting { inner: lazy(&Exact::<3>::from_integer(63)), reads: reads.clone() };
+        let quotient = lazy(&Exact::one()) / divisor.to_dyn();
+        let expected = PadicNumber::from_exact(&Exact::one() / &Exact::from_integer(63));
+        assert_eq!(digits(&quotient, -2..40), digits(&expected, -2..40));
+        // and the lowest one again, to find the scale
+        assert_eq!(reads.get(), 42 + 1);
+    }
+
+    #[test]
+    fn dividing_by_a_lazy_zero_builds_without_reading_it() {
+        let zero = lazy(&Exact::<2>::zero());
+        let _quotient = Padic::from(1u64) / zero.clone();
+        let _reciprocal = zero.reciprocal();
+    }
+
+    #[test]
+    fn building_on_a_quotient_by_a_lazy_zero_doesnt_read_it_either() {
+        let one = lazy(&Exact::<3>::one());
+        let quotient = &one / &lazy(&Exact::zero());
+        let _built = [
+            &quotient + &one,
+            &one - &quotient,
+            -&quotient,
+            &quotient * &one,
+            &one / &quotient,
+            quotient.reciprocal(),
+            [&quotient, &one].into_iter().sum(),
+            [&quotient, &one].into_iter().product(),
+        ];
+    }
+
+    #[test]
+    fn building_on_a_quotient_waits_for_zeros_past_the_ones_counted() {
+        // scale 0, but 39 low zeros
+        let power = Exact::<3>::from_integer(3_i64.pow(39));
+        let divisor = lazy(&(&power + &Exact::one())) - lazy(&Exact::one());
+        let one = lazy(&Exact::one());
+        let sum = &(&one / &divisor) + &one;
+        assert!(!sum.settle_scale());
+        let expected = PadicNumber::from_exact(&(&Exact::one() / &power) + &Exact::one());
+        assert_eq!(digits(&sum, -45..10), digits(&expected, -45..10));
+        assert_eq!(sum.get_scale(), expected.get_scale());
+        assert!(sum.settle_scale());
     }
 }
@@ -0,0 +1,249 @@
+use crate::discrete::Value;
+use crate::padic::{raise, CellGuard, ComputingGuard, PadicAccessor, PadicError, PadicNumber};
+use crate::padic_periodic::EventuallyPeriodicPadic;
+use std::cell::{Cell, RefCell};
+use std::ops::Range;
+use std::rc::{Rc, Weak};
+
+impl<'a, Digit: Value + 'a> PadicNumber<'a, Digit> {
+    /// The p-adic integer `x` with `x = definition(x)`, such as `x = 1 + p x^2`.
+    ///
+    /// Digit `n` of `x` is digit `n` of `definition(x)`, which is computed once digits `0..n` of
+    /// `x` are known. If that ends up asking for digit `n` of `x` or above, the definition is
+    /// ill-founded, and reading the digit panics with [`PadicError::IllFounded`] (see
+    /// [`Self::try_fill_digits`]). It panics with [`PadicError::NotAnInteger`] if the definition
+    /// has nonzero digits below 0.
+    ///
+    /// Nodes that read ahead of the digit they're asked for, like [`Self::reciprocal`], can make a
+    /// well-founded definition look ill-founded. `x` itself shouldn't outlive the definition.
+    pub fn fixed_point(definition: impl FnOnce(PadicNumber<'a, Digit>) -> PadicNumber<'a, Digit>) -> Self {
+        let knot = Rc::new_cyclic(|knot: &Weak<KnotPadicNumber<'a, Digit>>| {
+            let x = PadicNumber::new(KnotReference { knot: knot.clone() });
+            KnotPadicNumber {
+                definition: definition(x),
+                cache: RefCell::new(vec![]),
+                computing: Cell::new(false),
+            }
+        });
+        PadicNumber::new_from_rc(knot)
+    }
+
+    /// The fixed point of a contraction `f`, one with `|f(x) - f(y)| <= |x - y| / p` for all
+    /// p-adic integers `x` and `y`.
+    ///
+    /// See [`Self::contraction_fixed_point_with_rate`].
+    pub fn contraction_fixed_point(f: impl Fn(PadicNumber<'a, Digit>) -> PadicNumber<'a, Digit> + 'a) -> Self {
+        Self::contraction_fixed_point_with_rate(1, f)
+    }
+
+    /// The fixed point of `f`, given that `|f(x) - f(y)| <= |x - y| / p^rate` for all p-adic
+    /// integers `x` and `y`.
+    ///
+    /// Iterates `f` from 0, where each iteration fixes another `rate` digits, and only as many
+    /// iterations are made as the digits asked for need. The rate isn't checked, so a wrong one
+    /// gives wrong digits.
+    pub fn contraction_fixed_point_with_rate(
+        rate: usize,
+        f: impl Fn(PadicNumber<'a, Digit>) -> PadicNumber<'a, Digit> + 'a,
+    ) -> Self {
+        assert!(rate > 0, "A contraction has to fix at least one digit per iteration");
+        PadicNumber::new(ContractionPadicNumber::new(Box::new(f), rate))
+    }
+}
+
+/// Ties the knot: the digits of `definition`, which refers back to this node through a
+/// `KnotReference`.
+///
+/// Digits are computed one at a time, in order, and kept in a `RefCell` that's never borrowed
+/// while the definition runs, so that the definition can read the digits that are already known.
+pub struct KnotPadicNumber<'a, Digit: Value> {
+    definition: PadicNumber<'a, Digit>,
+    cache: RefCell<Vec<Digit>>,
+    computing: Cell<bool>,
+}
+
+impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for KnotPadicNumber<'a, Digit> {
+    fn get_digit(&self, index: isize) -> Digit {
+        let Ok(index) = usize::try_from(index) else {
+            return Digit::zero();
+        };
+        if let Some(&digit) = self.cache.borrow().get(index) {
+            return digit;
+        }
+        if self.computing.get() {
+            raise(PadicError::IllFounded);
+        }
+
+        self.computing.set(true);
+        let _computing = ComputingGuard(&self.computing);
+        if self.cache.borrow().is_empty() {
+            let scale = self.definition.get_scale();
+            if (scale..0).any(|i| !self.definition.get_digit(i).is_zero()) {
+                raise(PadicError::NotAnInteger);
+            }
+        }
+        while self.cache.borrow().len() <= index {
+            let next = self.cache.borrow().len() as isize;
+            let digit = self.definition.get_digit(next);
+            self.cache.borrow_mut().push(digit);
+        }
+
+        self.cache.borrow()[index]
+    }
+
+    fn get_scale(&self) -> isize {
+        0
+    }
+}
+
+/// How a definition sees the number it defines. It doesn't keep the number alive, which would
+/// make a reference cycle.
+struct KnotReference<'a, Digit: Value> {
+    knot: Weak<KnotPadicNumber<'a, Digit>>,
+}
+
+impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for KnotReference<'a, Digit> {
+    fn get_digit(&self, index: isize) -> Digit {
+        if index < 0 {
+            return Digit::zero();
+        }
+        // the knot isn't there yet while the definition is being built
+        match self.knot.upgrade() {
+            Some(knot) => knot.get_digit(index),
+            None => raise(PadicError::IllFounded),
+        }
+    }
+
+    fn get_scale(&self) -> isize {
+        0
+    }
+}
+
+/// The iterates `x_0 = 0`, `x_(k+1) = f(x_k)` of a contraction.
+///
+/// The fixed point `x` is as far from `x_0` as `x_1` is, and every iteration brings it `rate`
+/// digits closer, so `x` and `x_k` share every digit below `scale + k rate`, where `scale` is a
+/// lower bound on the valuation of `x_1 - x_0`.
+///
+/// Every iterate has the digits right that the ones before it do, so only the last one is kept,
+/// with its `k`. It's taken while `f` runs, so reading `x` from inside `f` panics with
+/// [`PadicError::IllFounded`].
+pub struct ContractionPadicNumber<'a, Digit: Value> {
+    f: Box<dyn Fn(PadicNumber<'a, Digit>) -> PadicNumber<'a, Digit> + 'a>,
+    rate: usize,
+    scale: isize,
+    iterate: Cell<Option<(usize, PadicNumber<'a, Digit>)>>,
+}
+
+impl<'a, Digit: Value + 'a> ContractionPadicNumber<'a, Digit> {
+    fn new(f: Box<dyn Fn(PadicNumber<'a, Digit>) -> PadicNumber<'a, Digit> + 'a>, rate: usize) -> Self {
+        let start = PadicNumber::from_exact(EventuallyPeriodicPadic::zero());
+        let next = f(start.clone());
+        let scale = (next.clone() - start.clone()).get_scale();
+        ContractionPadicNumber {
+            f,
+            rate,
+            scale,
+            iterate: Cell::new(Some((1, next))),
+        }
+    }
+
+    /// The first iterate that has the right digit at `index`.
+    fn iteration(&self, index: isize) -> usize {
+        if index < self.scale {
+            0
+        } else {
+            (index - self.scale) as usize / self.rate + 1
+        }
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for ContractionPadicNumber<'a, Digit> {
+    fn get_digit(&self, index: isize) -> Digit {
+        let mut digit = Digit::zero();
+        self.fill_digits(index..index + 1, std::slice::from_mut(&mut digit));
+        digit
+    }
+
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        if range.is_empty() {
+            return;
+        }
+        let mut guard = CellGuard::take(&self.iterate);
+        let Some((iteration, iterate)) = guard.value.as_mut() else {
+            raise(PadicError::IllFounded);
+        };
+        while *iteration < self.iteration(range.end - 1) {
+            *iterate = (self.f)(iterate.clone());
+            *iteration += 1;
+        }
+        iterate.fill_digits(range, digits);
+    }
+
+    fn get_scale(&self) -> isize {
+        self.scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::discrete::AddGroupU8;
+    use crate::padic::{PadicError, PadicNumber};
+    use crate::padic_test_util::{fraction, lazy, try_digits};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    type Padic<'a> = PadicNumber<'a, AddGroupU8<2>>;
+
+    #[test]
+    fn definition_can_read_below_zero_while_it_is_built() {
+        // x = 1 + 2x, so x = -1
+        let x = Padic::fixed_point(|x| {
+            assert_eq!(x.get_digit(-1).value(), 0);
+            Padic::from(1u8) + x.shift(1)
+        });
+        assert_eq!(try_digits(&x, -2..4), Ok(vec![0, 0, 1, 1, 1, 1]));
+    }
+
+    #[test]
+    fn contractions_find_their_fixed_points() {
+        type Padic3<'a> = PadicNumber<'a, AddGroupU8<3>>;
+        // x = 1/2 + 3x, which moves x and y together by a factor of 3, so x = -1/4
+        let half = lazy(fraction::<3>(1, 2).exact().unwrap());
+        let x = Padic3::contraction_fixed_point(move |x| half.clone() + x.shift(1));
+        assert_eq!(try_digits(&x, -2..30), try_digits(&fraction::<3>(-1, 4), -2..30));
+        // x = 1 + 9x, by a factor of 9, so x = -1/8
+        let one = lazy(fraction::<3>(1, 1).exact().unwrap());
+        let x = Padic3::contraction_fixed_point_with_rate(2, move |x| one.clone() + x.shift(2));
+        assert_eq!(try_digits(&x, -2..30), try_digits(&fraction::<3>(-1, 8), -2..30));
+        // x = 1 + 3x^2 isn't rational, but putting it back in gives it again
+        let x = Padic3::contraction_fixed_point(|x| fraction(1, 1) + (x.clone() * x).shift(1));
+        let residual = &x - &(fraction(1, 1) + (x.clone() * x.clone()).shift(1));
+        assert_eq!(try_digits(&residual, -2..30), Ok(vec![0; 32]));
+    }
+
+    #[test]
+    fn contraction_read_from_inside_itself_is_ill_founded() {
+        let this = Rc::new(RefCell::new(None::<Padic<'static>>));
+        let reference = this.clone();
+        let x = Padic::contraction_fixed_point(move |x| match reference.borrow().as_ref() {
+            Some(this) => x + Padic::from(this.get_digit(40).value()).shift(40),
+            None => x.shift(1),
+        });
+        *this.borrow_mut() = Some(x.clone());
+        assert_eq!(try_digits(&x, 0..10), Err(PadicError::IllFounded));
+        this.borrow_mut().take();
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one digit per iteration")]
+    fn contraction_rate_of_zero_is_rejected() {
+        Padic::contraction_fixed_point_with_rate(0, |x| x);
+    }
+
+    #[test]
+    fn reciprocal_of_itself_is_ill_founded() {
+        let x = Padic::fixed_point(|x| Padic::from(1u8) / x);
+        assert_eq!(try_digits(&x, 0..1), Err(PadicError::IllFounded));
+    }
+}
@@ -0,0 +1,213 @@
+use crate::discrete::{AddGroupU8, Value, Zero};
+use crate::padic::{PadicError, PadicNumber};
+use crate::padic_periodic::EventuallyPeriodicPadic;
+
+/// Fixed-width native integers that can be read off the low digits of a 2-adic integer.
+pub trait TwosComplement: Copy {
+    const BITS: u32;
+    const SIGNED: bool;
+
+    /// Returns the two's complement bits of `self`, sign-extended to 128 bits, and whether it is
+    /// negative (meaning every bit past the 128th is also set).
+    fn to_bits(self) -> (u128, bool);
+
+    /// Keeps the low `Self::BITS` bits, the same way `as` does.
+    fn from_bits(bits: u128) -> Self;
+}
+
+macro_rules! impl_twos_complement {
+    ($signed:expr => $($int:ty),*) => {$(
+        impl TwosComplement for $int {
+            const BITS: u32 = <$int>::BITS;
+            const SIGNED: bool = $signed;
+
+            #[allow(unused_comparisons)]
+            fn to_bits(self) -> (u128, bool) {
+                (self as i128 as u128, self < 0)
+            }
+
+            fn from_bits(bits: u128) -> Self {
+                bits as $int
+            }
+        }
+
+        /// Negative values continue with an infinite run of ones, exactly like sign extension.
+        impl<'a> From<$int> for PadicNumber<'a, AddGroupU8<2>> {
+            fn from(value: $int) -> Self {
+                let (bits, negative) = value.to_bits();
+                PadicNumber::from_exact(EventuallyPeriodicPadic::new(
+                    0,
+                    (0..128).map(|i| AddGroupU8::from_bool((bits >> i) & 1 == 1)).collect(),
+                    vec![AddGroupU8::from_bool(negative)],
+                ))
+            }
+        }
+    )*};
+}
+
+impl_twos_complement!(true => i8, i16, i32, i64, i128, isize);
+impl_twos_complement!(false => u8, u16, u32, u64, u128, usize);
+
+impl<'a> PadicNumber<'a, AddGroupU8<2>> {
+    /// Reads the low `T::BITS` digits as a two's complement integer, dropping every other digit
+    /// (including any digits after the point), the same way `as` truncates between native ints.
+    pub fn wrapping_to_int<T: TwosComplement>(&self) -> T {
+        let mut bits = 0u128;
+        for i in 0..T::BITS {
+            if !self.value.get_digit(i as isize).is_zero() {
+                bits |= 1 << i;
+            }
+        }
+        T::from_bits(bits)
+    }
+
+    /// Like [`Self::wrapping_to_int`], but fails instead of dropping digits.
+    ///
+    /// Digits after the point have to be zero, and every digit from the top bit of `T` on has to
+    /// match the sign of `T` (zero for unsigned types). For exact numbers that's read off the
+    /// period and the scale. A lazy number is taken to be what its digits below `view_size` say,
+    /// like [`Self::distance`] does, so it fits if those digits from the top bit of `T` up match
+    /// the sign. Only the digits from `-view_size` to `view_size` are read: this fails with
+    /// [`PadicError::Undecided`] if they don't reach the top bit of `T`, or if every digit after
+    /// the point they reach is zero but there are more.
+    pub fn checked_to_int<T: TwosComplement>(&self, view_size: isize) -> Result<T, PadicError> {
+        let sign_index = if T::SIGNED { T::BITS as isize - 1 } else { T::BITS as isize };
+        if let Some(exact) = self.exact() {
+            return if exact.scale() < 0 {
+                Err(PadicError::NotAnInteger)
+            } else if fits(exact, sign_index, T::SIGNED) {
+                Ok(self.wrapping_to_int())
+            } else {
+                Err(PadicError::OutOfRange)
+            };
+        }
+        let fraction_start = self.get_scale().max(view_size.saturating_neg()).min(0);
+        let mut fraction = vec![AddGroupU8::zero(); fraction_start.unsigned_abs()];
+        self.fill_digits(fraction_start..0, &mut fraction);
+        if fraction.iter().any(|digit| !digit.is_zero()) {
+            return Err(PadicError::NotAnInteger);
+        }
+        let mut high = vec![AddGroupU8::zero(); view_size.saturating_sub(sign_index).max(0) as usize];
+        self.fill_digits(sign_index..sign_index + high.len() as isize, &mut high);
+        if let Some(&sign) = high.first()
+            && (!(T::SIGNED || sign.is_zero()) || high.iter().any(|&digit| digit != sign))
+        {
+            return Err(PadicError::OutOfRange);
+        }
+        if high.is_empty() || self.get_scale() < fraction_start {
+            return Err(PadicError::Undecided { view_size });
+        }
+        Ok(self.wrapping_to_int())
+    }
+}
+
+/// Whether every digit of an exact integer from `sign_index` on is the same sign digit.
+///
+/// That needs a period of one digit, which the canonical form puts right above the last digit
+/// that differs from it: the top of the pre-period, or the zero just below the scale of a number
+/// that is all ones.
+fn fits(exact: &EventuallyPeriodicPadic<AddGroupU8<2>>, sign_index: isize, signed: bool) -> bool {
+    let &[sign] = exact.period() else {
+        return false;
+    };
+    if !signed && !sign.is_zero() {
+        return false;
+    }
+    let highest_other = if !exact.pre_period().is_empty() {
+        exact.scale().saturating_add(exact.pre_period().len() as isize - 1)
+    } else if sign.is_zero() {
+        return true;
+    } else {
+        exact.scale() - 1
+    };
+    highest_other < sign_index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::padic_test_util::{lazy, Exact};
+
+    type Padic<'a> = PadicNumber<'a, AddGroupU8<2>>;
+
+    fn round_trip<T: TwosComplement + Into<Padic<'static>> + PartialEq + std::fmt::Debug>(values: &[T]) {
+        for &value in values {
+            let number: Padic = value.into();
+            assert_eq!(number.wrapping_to_int::<T>(), value);
+            assert_eq!(number.checked_to_int::<T>(0), Ok(value));
+            let lazy = lazy(number.exact().unwrap());
+            assert_eq!(lazy.checked_to_int::<T>(T::BITS as isize + 1), Ok(value));
+            assert_eq!(lazy.checked_to_int::<T>(200), Ok(value));
+        }
+    }
+
+    #[test]
+    fn native_integers_round_trip() {
+        round_trip(&[0i8, 1, -1, i8::MIN, i8::MAX]);
+        round_trip(&[0i64, 12345, -12345, i64::MIN, i64::MAX]);
+        round_trip(&[0i128, -7, i128::MIN, i128::MAX]);
+        round_trip(&[0u8, 1, u8::MAX]);
+        round_trip(&[0u64, 1 << 40, u64::MAX]);
+        round_trip(&[0u128, u128::MAX]);
+        round_trip(&[0isize, -1, isize::MIN, isize::MAX]);
+        round_trip(&[0usize, usize::MAX]);
+    }
+
+    #[test]
+    fn wrapping_truncates_like_as() {
+        assert_eq!(Padic::from(300u32).wrapping_to_int::<u8>(), 300u32 as u8);
+        assert_eq!(Padic::from(-1i8).wrapping_to_int::<u64>(), -1i8 as u64);
+        assert_eq!(Padic::from(u64::MAX).wrapping_to_int::<i32>(), u64::MAX as i32);
+        for x in [1u32, 3, 5, 7, 12345, 0xdeadbeef] {
+            let inverse: u32 = (Padic::from(1u32) / Padic::from(x)).wrapping_to_int();
+            assert_eq!(inverse.wrapping_mul(x), 1);
+        }
+    }
+
+    #[test]
+    fn checked_rejects_what_does_not_fit() {
+        assert_eq!(Padic::from(300u32).checked_to_int::<u8>(0), Err(PadicError::OutOfRange));
+        assert_eq!(Padic::from(128u8).checked_to_int::<i8>(0), Err(PadicError::OutOfRange));
+        assert_eq!(Padic::from(-1i8).checked_to_int::<u64>(0), Err(PadicError::OutOfRange));
+        assert_eq!(Padic::from(i8::MIN).checked_to_int::<i8>(0), Ok(i8::MIN));
+        let half = Padic::from(1u8) / Padic::from(2u8);
+        assert_eq!(half.checked_to_int::<u8>(0), Err(PadicError::NotAnInteger));
+        let third = Padic::from(1u8) / Padic::from(3u8);
+        assert_eq!(third.checked_to_int::<i64>(0), Err(PadicError::OutOfRange));
+        assert_eq!(lazy(half.exact().unwrap()).checked_to_int::<u8>(20), Err(PadicError::NotAnInteger));
+    }
+
+    #[test]
+    fn lazy_numbers_are_read_up_to_the_view_size() {
+        let big = lazy(&Exact::from_integer(1 << 40));
+        assert_eq!(big.checked_to_int::<u32>(20), Err(PadicError::Undecided { view_size: 20 }));
+        assert_eq!(big.checked_to_int::<u32>(32), Err(PadicError::Undecided { view_size: 32 }));
+        // digit 40 is past the view
+        assert_eq!(big.checked_to_int::<u32>(33), Ok(0));
+        assert_eq!(big.checked_to_int::<u32>(41), Err(PadicError::OutOfRange));
+        assert_eq!(big.checked_to_int::<u64>(65), Ok(1 << 40));
+        let minus_one = lazy(&Exact::from_integer(-1));
+        assert_eq!(minus_one.checked_to_int::<i8>(8), Ok(-1));
+        assert_eq!(minus_one.checked_to_int::<u8>(9), Err(PadicError::OutOfRange));
+    }
+
+    #[test]
+    fn lazy_digits_far_after_the_point_are_not_read() {
+        let tiny = lazy(&Exact::one()).shift(-(1 << 34));
+        assert_eq!(tiny.checked_to_int::<u8>(20), Err(PadicError::Undecided { view_size: 20 }));
+        assert_eq!(tiny.checked_to_int::<u8>(isize::MIN), Err(PadicError::Undecided { view_size: isize::MIN }));
+    }
+
+    #[test]
+    fn far_exact_digits_are_decided_without_reading_them() {
+        let far = Padic::from(1u8).shift(1 << 34);
+        assert_eq!(far.checked_to_int::<u32>(0), Err(PadicError::OutOfRange));
+        assert_eq!(far.shift(-(1 << 35)).checked_to_int::<u32>(0), Err(PadicError::NotAnInteger));
+        let minus_far = -&far;
+        assert_eq!(minus_far.checked_to_int::<i64>(0), Err(PadicError::OutOfRange));
+        assert_eq!(Padic::from(-1i8).shift(40).checked_to_int::<i64>(0), Ok(-1 << 40));
+        assert_eq!(Padic::from(-1i8).shift(63).checked_to_int::<i64>(0), Ok(i64::MIN));
+        assert_eq!(Padic::from(-1i8).shift(64).checked_to_int::<i64>(0), Err(PadicError::OutOfRange));
+        assert_eq!(Padic::from(1u8).shift(63).checked_to_int::<u64>(0), Ok(1 << 63));
+    }
+}
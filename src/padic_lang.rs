@@ -1,8 +1,10 @@
-use crate::discrete::{AddGroupU8, One, Zero};
-use crate::padic::{PadicAccessor, PadicNumber};
-use crate::padic_primitive::{FinitePadicInteger, RepeatingPadicInteger};
+use crate::discrete::AddGroupU8;
+use crate::padic::{PadicAccessor, PadicError, PadicNumber};
+use crate::padic_laurent::LaurentSeries;
+use crate::padic_periodic::EventuallyPeriodicPadic;
 use logos::Logos;
 use std::collections::HashMap;
+use std::ops::{Add, Div, Mul, Neg, Sub};
 
 #[derive(Logos, Debug, PartialEq)]
 #[logos(skip r"[ \t\n\f]+")] // Ignore this regex pattern between tokens
@@ -17,6 +19,12 @@ enum PadicToken {
     DivisionSign,
     #[token("^^")]
     Square,
+    #[token("~")]
+    Negation,
+    #[regex(r"<<[0-9]*")]
+    ShiftLeft,
+    #[regex(r">>[0-9]*")]
+    ShiftRight,
     #[regex(r"_*([0-9]|\([0-9]*\))+(\.([0-9]|\([0-9]*\))*)?")]
     Number,
     #[regex(r"&(\{\w*\})?")]
@@ -27,13 +35,69 @@ enum PadicToken {
     RightBracket,
 }
 
+/// Anything the language can compute with: number literals turn into it, and the operators
+/// map onto its arithmetic.
+pub trait Parseable<const BASE: u8>:
+    Clone + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self> + Neg<Output = Self>
+{
+    /// A number literal, which always has eventually periodic digits.
+    fn from_literal(literal: EventuallyPeriodicPadic<AddGroupU8<BASE>>) -> Self;
+
+    /// What `<<k` (and `>>k`, with `-k`) does.
+    fn shift(&self, amount: isize) -> Self;
+
+    /// Whether this is known to be zero, so that dividing by it can be refused up front.
+    fn is_exact_zero(&self) -> bool;
+}
+
+impl<'a, const BASE: u8> Parseable<BASE> for PadicNumber<'a, AddGroupU8<BASE>> {
+    fn from_literal(literal: EventuallyPeriodicPadic<AddGroupU8<BASE>>) -> Self {
+        literal.to_dyn()
+    }
+
+    fn shift(&self, amount: isize) -> Self {
+        PadicNumber::shift(self, amount)
+    }
+
+    fn is_exact_zero(&self) -> bool {
+        self.exact().is_some_and(EventuallyPeriodicPadic::is_zero)
+    }
+}
+
+/// The same literals read as Laurent series, so `_1.1` is `1/(1 - t) + t^-1`, in F_p((t)).
+impl<'a, const BASE: u8> Parseable<BASE> for LaurentSeries<'a, AddGroupU8<BASE>> {
+    fn from_literal(literal: EventuallyPeriodicPadic<AddGroupU8<BASE>>) -> Self {
+        LaurentSeries::from_digits(literal.to_dyn())
+    }
+
+    fn shift(&self, amount: isize) -> Self {
+        LaurentSeries::shift(self, amount)
+    }
+
+    fn is_exact_zero(&self) -> bool {
+        self.digits().exact().is_some_and(EventuallyPeriodicPadic::is_zero)
+    }
+}
+
 pub fn parse_padic<'a, const BASE: u8>(
     string: &str,
     arguments: &HashMap<String, PadicNumber<'a, AddGroupU8<BASE>>>,
 ) -> Result<PadicNumber<'a, AddGroupU8<BASE>>, String> {
+    parse(string, arguments)
+}
+
+/// Like [`parse_padic`], but computes in F_p((t)) instead of Q_p.
+pub fn parse_laurent<'a, const BASE: u8>(
+    string: &str,
+    arguments: &HashMap<String, LaurentSeries<'a, AddGroupU8<BASE>>>,
+) -> Result<LaurentSeries<'a, AddGroupU8<BASE>>, String> {
+    parse(string, arguments)
+}
+
+pub fn parse<const BASE: u8, T: Parseable<BASE>>(string: &str, arguments: &HashMap<String, T>) -> Result<T, String> {
     let mut lex = PadicToken::lexer(string);
 
-    let mut stack: Vec<PadicNumber<'a, AddGroupU8<BASE>>> = vec![];
+    let mut stack: Vec<T> = vec![];
 
     while let Some(token) = lex.next() {
         match token {
@@ -41,8 +105,22 @@ pub fn parse_padic<'a, const BASE: u8>(
                 PadicToken::AdditionSign => binary_operator(&mut stack, |a, b| a + b)?,
                 PadicToken::SubtractionSign => binary_operator(&mut stack, |a, b| a - b)?,
                 PadicToken::MultiplicationSign => binary_operator(&mut stack, |a, b| a * b)?,
-                PadicToken::DivisionSign => binary_operator(&mut stack, |a, b| a / b)?,
+                PadicToken::DivisionSign => {
+                    if stack.last().is_some_and(T::is_exact_zero) {
+                        return Err(PadicError::DivisionByZero.to_string());
+                    }
+                    binary_operator(&mut stack, |a, b| a / b)?
+                }
                 PadicToken::Square => unary_operator(&mut stack, |a| a.clone() * a)?,
+                PadicToken::Negation => unary_operator(&mut stack, |a| -a)?,
+                PadicToken::ShiftLeft => {
+                    let amount = shift_amount(lex.slice())?;
+                    unary_operator(&mut stack, |a| a.shift(amount))?
+                }
+                PadicToken::ShiftRight => {
+                    let amount = shift_amount(lex.slice())?;
+                    unary_operator(&mut stack, |a| a.shift(-amount))?
+                }
                 PadicToken::LeftBracket => return Err("Brackets not supported yet!".into()),
                 PadicToken::RightBracket => return Err("Brackets not supported yet!".into()),
                 PadicToken::Reference => {
@@ -57,148 +135,10 @@ pub fn parse_padic<'a, const BASE: u8>(
                         None => return Err(format!("Unknown variable \"{}\"", reference)),
                     }
                 }
-                PadicToken::Number => {
-                    let number_str = lex.slice();
-                    enum Stage {
-                        Repeating,
-                        ExpectingDigit,
-                        InParenthesis(Vec<u8>),
-                    }
-                    impl Stage {
-                        fn can_accept_digit(&self) -> bool {
-                            match self {
-                                Self::Repeating => true,
-                                Self::ExpectingDigit => true,
-                                _ => false,
-                            }
-                        }
-                    }
-                    let digit_too_big = |digit: u8| -> String {
-                        format!(
-                            "Too big of a digit for the base! (digit: {}) (base: {})",
-                            digit, BASE
-                        )
-                    };
-                    let mut stage = Stage::Repeating;
-                    let mut decimal_index = None;
-                    let mut repeat_count: usize = 0;
-                    let mut digit_vec: Vec<AddGroupU8<BASE>> = vec![];
-
-                    for &char in number_str.as_bytes() {
-                        if char == '_' as u8 {
-                            if let Stage::Repeating = stage {
-                                repeat_count += 1
-                            } else {
-                                return Err(format!(
-                                    "Didn't expect repetition digit inside a number (in string: \"{}\")",
-                                    number_str
-                                ));
-                            }
-                        } else if char == '(' as u8 {
-                            if stage.can_accept_digit() {
-                                stage = Stage::InParenthesis(vec![])
-                            } else {
-                                return Err(format!(
-                                    "Not expecting digit but found '(' (in string: \"{}\")",
-                                    number_str
-                                ));
-                            }
-                        } else if char == ')' as u8 {
-                            if let Stage::InParenthesis(ref digits) = stage {
-                                let mut sum: u8 = 0;
-                                let mut pow = Some(1u8);
-                                for digit in digits.iter().rev() {
-                                    if let Some(pow) = pow
-                                        && let Some(scaled) = digit.checked_mul(pow)
-                                        && let Some(new_sum) = sum.checked_add(scaled)
-                                    {
-                                        sum = new_sum;
-                                    } else {
-                                        return Err(format!(
-                                            "The number got too big (value was {}) (in string: \"{}\")",
-                                            digits.iter().fold("".to_owned(), |acc, &v| format!(
-                                                "{}{}",
-                                                v, acc
-                                            )),
-                                            number_str
-                                        ));
-                                    }
-                                    pow = pow.and_then(|pow| pow.checked_mul(10));
-                                }
-                                digit_vec
-                                    .push(AddGroupU8::new(sum).ok_or_else(|| digit_too_big(sum))?);
-                                stage = Stage::ExpectingDigit;
-                            } else {
-                                return Err(format!(
-                                    "Not expecting digit but found '(' (in string: \"{}\")",
-                                    number_str
-                                ));
-                            }
-                        } else if char == '.' as u8 {
-                            if let Some(_) = decimal_index {
-                                return Err(format!(
-                                    "Cannot have two decimal places in number (in string: \"{}\")",
-                                    number_str
-                                ));
-                            }
-                            decimal_index = Some(digit_vec.len());
-                        } else {
-                            let digit = char - '0' as u8;
-                            if digit <= 9 {
-                                if stage.can_accept_digit() {
-                                    digit_vec.push(
-                                        AddGroupU8::new(digit)
-                                            .ok_or_else(|| digit_too_big(digit))?,
-                                    );
-                                    stage = Stage::ExpectingDigit;
-                                } else if let Stage::InParenthesis(ref mut digits) = stage {
-                                    digits.push(digit);
-                                }
-                            } else {
-                                return Err(format!(
-                                    "Unexpected number character: {} (as u8: {}) (in string: \"{}\")",
-                                    char as char, char, number_str
-                                ));
-                            }
-                        }
-                    }
-                    let scale = decimal_index.map(|x| digit_vec.len() - x);
-                    let unscaled_number = if repeat_count == 0 {
-                        digit_vec.reverse();
-                        FinitePadicInteger::new_with_digits(digit_vec).to_dyn()
-                    } else if repeat_count == digit_vec.len() {
-                        digit_vec.reverse();
-                        RepeatingPadicInteger::new_with_digits(digit_vec).to_dyn()
-                    } else if repeat_count < digit_vec.len() {
-                        let repeating_digit_vec =
-                            digit_vec.iter().copied().take(repeat_count).rev().collect();
-                        let non_repeating_digit_vec =
-                            digit_vec.iter().copied().skip(repeat_count).rev().collect();
-                        let mut power_vec =
-                            vec![AddGroupU8::zero(); digit_vec.len() - repeat_count];
-                        power_vec.push(AddGroupU8::one());
-                        RepeatingPadicInteger::new_with_digits(repeating_digit_vec).to_dyn()
-                            * FinitePadicInteger::new_with_digits(power_vec).to_dyn()
-                            + FinitePadicInteger::new_with_digits(non_repeating_digit_vec).to_dyn()
-                    } else {
-                        return Err(format!(
-                            "You cannot repeat more digits than you have (number: {})",
-                            number_str
-                        ));
-                    };
-                    stack.push(match scale {
-                        Some(scale) => {
-                            let mut power_vec = vec![AddGroupU8::zero(); scale];
-                            power_vec.push(AddGroupU8::one());
-                            unscaled_number
-                                / FinitePadicInteger::new_with_digits(power_vec).to_dyn()
-                        }
-                        None => unscaled_number,
-                    });
-                }
+                PadicToken::Number => stack.push(T::from_literal(parse_literal(lex.slice())?)),
             },
             Err(_) => {
-                return Err(format!("Unknown token: {}", lex.slice().to_string()));
+                return Err(format!("Unknown token: {}", lex.slice()));
             }
         }
     }
@@ -209,22 +149,173 @@ pub fn parse_padic<'a, const BASE: u8>(
     }
 }
 
-fn binary_operator<T: Clone>(stack: &mut Vec<T>, func: fn(T, T) -> T) -> Result<(), String> {
+/// Reads a number literal like `_01.1`, whose digits are written from the most significant
+/// one down, the repeating ones first, marked by one `_` each.
+fn parse_literal<const BASE: u8>(number_str: &str) -> Result<EventuallyPeriodicPadic<AddGroupU8<BASE>>, String> {
+    enum Stage {
+        Repeating,
+        ExpectingDigit,
+        InParenthesis(Vec<u8>),
+    }
+    impl Stage {
+        fn can_accept_digit(&self) -> bool {
+            matches!(self, Self::Repeating | Self::ExpectingDigit)
+        }
+    }
+    let digit_too_big = |digit: u8| -> String {
+        format!(
+            "Too big of a digit for the base! (digit: {}) (base: {})",
+            digit, BASE
+        )
+    };
+    let mut stage = Stage::Repeating;
+    let mut decimal_index = None;
+    let mut repeat_count: usize = 0;
+    let mut digit_vec: Vec<AddGroupU8<BASE>> = vec![];
+
+    for &char in number_str.as_bytes() {
+        if char == b'_' {
+            if let Stage::Repeating = stage {
+                repeat_count += 1
+            } else {
+                return Err(format!(
+                    "Didn't expect repetition digit inside a number (in string: \"{}\")",
+                    number_str
+                ));
+            }
+        } else if char == b'(' {
+            if stage.can_accept_digit() {
+                stage = Stage::InParenthesis(vec![])
+            } else {
+                return Err(format!(
+                    "Not expecting digit but found '(' (in string: \"{}\")",
+                    number_str
+                ));
+            }
+        } else if char == b')' {
+            if let Stage::InParenthesis(ref digits) = stage {
+                let mut sum: u8 = 0;
+                let mut pow = Some(1u8);
+                for digit in digits.iter().rev() {
+                    if let Some(pow) = pow
+                        && let Some(scaled) = digit.checked_mul(pow)
+                        && let Some(new_sum) = sum.checked_add(scaled)
+                    {
+                        sum = new_sum;
+                    } else {
+                        return Err(format!(
+                            "The number got too big (value was {}) (in string: \"{}\")",
+                            digits.iter().fold("".to_owned(), |acc, &v| format!(
+                                "{}{}",
+                                v, acc
+                            )),
+                            number_str
+                        ));
+                    }
+                    pow = pow.and_then(|pow| pow.checked_mul(10));
+                }
+                digit_vec
+                    .push(AddGroupU8::new(sum).ok_or_else(|| digit_too_big(sum))?);
+                stage = Stage::ExpectingDigit;
+            } else {
+                return Err(format!(
+                    "Not expecting digit but found '(' (in string: \"{}\")",
+                    number_str
+                ));
+            }
+        } else if char == b'.' {
+            if decimal_index.is_some() {
+                return Err(format!(
+                    "Cannot have two decimal places in number (in string: \"{}\")",
+                    number_str
+                ));
+            }
+            decimal_index = Some(digit_vec.len());
+        } else {
+            let digit = char - b'0';
+            if digit <= 9 {
+                if stage.can_accept_digit() {
+                    digit_vec.push(
+                        AddGroupU8::new(digit)
+                            .ok_or_else(|| digit_too_big(digit))?,
+                    );
+                    stage = Stage::ExpectingDigit;
+                } else if let Stage::InParenthesis(ref mut digits) = stage {
+                    digits.push(digit);
+                }
+            } else {
+                return Err(format!(
+                    "Unexpected number character: {} (as u8: {}) (in string: \"{}\")",
+                    char as char, char, number_str
+                ));
+            }
+        }
+    }
+    if repeat_count > digit_vec.len() {
+        return Err(format!(
+            "You cannot repeat more digits than you have (number: {})",
+            number_str
+        ));
+    }
+    let scale = decimal_index.map_or(0, |x| digit_vec.len() - x);
+    let non_repeating_digit_vec = digit_vec.split_off(repeat_count);
+    Ok(EventuallyPeriodicPadic::new(
+        -(scale as isize),
+        non_repeating_digit_vec.into_iter().rev().collect(),
+        digit_vec.into_iter().rev().collect(),
+    ))
+}
+
+fn binary_operator<T>(stack: &mut Vec<T>, func: fn(T, T) -> T) -> Result<(), String> {
     if let Some(b) = stack.pop()
         && let Some(a) = stack.pop()
     {
-        stack.push(func(a.clone(), b.clone()));
+        stack.push(func(a, b));
         Ok(())
     } else {
         Err("Too few on stack!".into())
     }
 }
 
-fn unary_operator<T: Clone>(stack: &mut Vec<T>, func: fn(T) -> T) -> Result<(), String> {
+fn unary_operator<T>(stack: &mut Vec<T>, func: impl FnOnce(T) -> T) -> Result<(), String> {
     if let Some(a) = stack.pop() {
-        stack.push(func(a.clone()));
+        stack.push(func(a));
         Ok(())
     } else {
         Err("Too few on stack!".into())
     }
 }
+
+fn shift_amount(token: &str) -> Result<isize, String> {
+    if token.len() == 2 {
+        Ok(1)
+    } else {
+        token[2..]
+            .parse()
+            .map_err(|_| format!("Shift amount too big (in string: \"{}\")", token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::padic_shift::ShiftedPadicNumber;
+
+    #[test]
+    fn long_sums_do_not_overflow_the_stack() {
+        let one = PadicNumber::<AddGroupU8<2>>::from(1u64);
+        let lazy_one = PadicNumber::new(ShiftedPadicNumber::new(one, 0));
+        let arguments = HashMap::from([("x".to_string(), lazy_one)]);
+        let script = format!("&{{x}}{}", " &{x} +".repeat(100_000));
+        let sum = parse_padic(&script, &arguments).unwrap();
+        let value = (0..20).fold(0u64, |acc, i| acc | (sum.value.get_digit(i).value() as u64) << i);
+        assert_eq!(value, 100_001);
+    }
+
+    #[test]
+    fn dividing_by_an_exact_zero_is_an_error() {
+        let arguments = HashMap::new();
+        assert!(parse_padic::<2>("1 0 /", &arguments).is_err());
+        assert!(parse_padic::<2>("1 1 1 - /", &arguments).is_err());
+    }
+}
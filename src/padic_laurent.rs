@@ -0,0 +1,351 @@
+use crate::discrete::Value;
+use crate::padic::{
+    raise, CellGuard, PadicAccessor, PadicError, PadicIntegerAccessor, PadicIntegerToNumber, PadicNumber,
+    PadicNumberScaler, PadicNumberView,
+};
+use crate::padic_div::count_low_zeros;
+use crate::padic_periodic::EventuallyPeriodicPadic;
+use std::cell::Cell;
+use std::ops::{Add, Div, Mul, Neg, Range, Sub};
+use std::rc::Rc;
+
+/// A Laurent series over F_p, `sum a_i t^i`, stored the same way as a p-adic number.
+///
+/// F_p((t)) has the same digits as Q_p, with `t` in place of `p`, but adding and multiplying
+/// never carries. The accessors here mirror the p-adic ones with plain digit arithmetic, so
+/// everything that only looks at digits (views, shifts, parsing) works for both.
+///
+/// Dividing by an exact zero panics with [`PadicError::DivisionByZero`]. Like for p-adic numbers,
+/// a lazy zero can't be told apart from a series with a lot of low zero coefficients, so reading
+/// a quotient by one never finishes.
+pub struct LaurentSeries<'a, Digit: Value> {
+    digits: PadicNumber<'a, Digit>,
+}
+
+impl<'a, Digit: Value + 'a> LaurentSeries<'a, Digit> {
+    /// Reads digit `i` of `digits` as the coefficient of `t^i`.
+    pub fn from_digits(digits: PadicNumber<'a, Digit>) -> Self {
+        LaurentSeries { digits }
+    }
+
+    pub fn new<Accessor: PadicAccessor<'a, Digit> + 'a>(value: Accessor) -> Self {
+        Self::from_digits(PadicNumber::new(value))
+    }
+
+    /// The p-adic number with the same digits.
+    pub fn digits(&self) -> &PadicNumber<'a, Digit> {
+        &self.digits
+    }
+
+    pub fn as_view(&self, view_size: isize) -> PadicNumberView<'a, Digit> {
+        self.digits.as_view(view_size)
+    }
+
+    pub fn get_scale(&self) -> isize {
+        self.digits.get_scale()
+    }
+
+    /// The lowest power of `t` with a nonzero coefficient, looking no further than `view_size`.
+    pub fn valuation(&self, view_size: isize) -> Option<isize> {
+        self.digits.valuation(view_size)
+    }
+
+    /// Multiplies by `t^k`, which is the same digit shift as multiplying a p-adic number by `p^k`.
+    pub fn shift(&self, k: isize) -> Self {
+        Self::from_digits(self.digits.shift(k))
+    }
+}
+
+impl<'a, Digit: Value> Clone for LaurentSeries<'a, Digit> {
+    fn clone(&self) -> Self {
+        LaurentSeries {
+            digits: self.digits.clone(),
+        }
+    }
+}
+
+fn add<'a, Digit: Value + 'a>(
+    lhs: LaurentSeries<'a, Digit>,
+    rhs: LaurentSeries<'a, Digit>,
+    subtract: bool,
+) -> LaurentSeries<'a, Digit> {
+    LaurentSeries::new(CarryFreeAdditivePadicNumber {
+        lhs: lhs.digits,
+        rhs: rhs.digits,
+        subtract,
+    })
+}
+
+fn mul<'a, Digit: Value + 'a>(lhs: LaurentSeries<'a, Digit>, rhs: LaurentSeries<'a, Digit>) -> LaurentSeries<'a, Digit> {
+    let (lhs_scale, rhs_scale) = (lhs.get_scale(), rhs.get_scale());
+    let inner = CarryFreeMultiplicationPadicInteger::new(
+        Rc::new(PadicNumberScaler::new(&lhs.digits, lhs_scale)),
+        Rc::new(PadicNumberScaler::new(&rhs.digits, rhs_scale)),
+    );
+    LaurentSeries::new(PadicIntegerToNumber::new(Rc::new(inner), lhs_scale + rhs_scale))
+}
+
+fn div<'a, Digit: Value + 'a>(lhs: LaurentSeries<'a, Digit>, rhs: LaurentSeries<'a, Digit>) -> LaurentSeries<'a, Digit> {
+    if rhs.digits.exact().is_some_and(EventuallyPeriodicPadic::is_zero) {
+        raise(PadicError::DivisionByZero);
+    }
+    let (lhs_scale, rhs_scale) = (lhs.get_scale(), rhs.get_scale());
+    let inner = CarryFreeDivisionPadicInteger::new(
+        Rc::new(PadicNumberScaler::new(&lhs.digits, lhs_scale)),
+        Rc::new(PadicNumberScaler::new(&rhs.digits, rhs_scale)),
+    );
+    LaurentSeries::new(inner).shift(lhs_scale - rhs_scale)
+}
+
+/// Adds or subtracts digit by digit. Without carries, no digit depends on any other, so there's
+/// nothing to cache.
+pub struct CarryFreeAdditivePadicNumber<'a, Digit: Value> {
+    lhs: PadicNumber<'a, Digit>,
+    rhs: PadicNumber<'a, Digit>,
+    subtract: bool,
+}
+
+impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for CarryFreeAdditivePadicNumber<'a, Digit> {
+    fn get_digit(&self, index: isize) -> Digit {
+        let mut digit = Digit::zero();
+        self.fill_digits(index..index + 1, std::slice::from_mut(&mut digit));
+        digit
+    }
+
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        let mut rhs = vec![Digit::zero(); digits.len()];
+        self.lhs.value.fill_digits(range.clone(), digits);
+        self.rhs.value.fill_digits(range, &mut rhs);
+        for (digit, rhs_digit) in digits.iter_mut().zip(rhs) {
+            *digit = if self.subtract { *digit - rhs_digit } else { *digit + rhs_digit };
+        }
+    }
+
+    fn get_scale(&self) -> isize {
+        self.lhs.get_scale().min(self.rhs.get_scale())
+    }
+}
+
+/// Coefficient `n` of the product is `sum a_k b_(n-k)`, which is just the schoolbook column
+/// without a carry into the next one.
+pub(crate) struct CarryFreeMultiplicationPadicInteger<'a, Digit: Value> {
+    lhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
+    rhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
+    cache: Cell<(Vec<Digit>, Vec<Digit>, Vec<Digit>)>,
+}
+
+impl<'a, Digit: Value> CarryFreeMultiplicationPadicInteger<'a, Digit> {
+    pub(crate) fn new(
+        lhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
+        rhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
+    ) -> CarryFreeMultiplicationPadicInteger<'a, Digit> {
+        CarryFreeMultiplicationPadicInteger {
+            lhs,
+            rhs,
+            cache: Cell::new((vec![], vec![], vec![])),
+        }
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicIntegerAccessor<'a, Digit> for CarryFreeMultiplicationPadicInteger<'a, Digit> {
+    fn get_integer_digit(&self, index: usize) -> Digit {
+        let mut digit = Digit::zero();
+        self.fill_integer_digits(index..index + 1, std::slice::from_mut(&mut digit));
+        digit
+    }
+
+    fn fill_integer_digits(&self, range: Range<usize>, digits: &mut [Digit]) {
+        let mut cache = CellGuard::take(&self.cache);
+        let (lhs, rhs, computed) = &mut cache.value;
+
+        let start = computed.len();
+        if start < range.end {
+            lhs.resize(range.end, Digit::zero());
+            rhs.resize(range.end, Digit::zero());
+            self.lhs.fill_integer_digits(start..range.end, &mut lhs[start..]);
+            self.rhs.fill_integer_digits(start..range.end, &mut rhs[start..]);
+            for n in start..range.end {
+                let digit = (0..=n).fold(Digit::zero(), |sum, k| sum + lhs[k] * rhs[n - k]);
+                computed.push(digit);
+            }
+        }
+        digits.copy_from_slice(&computed[range]);
+    }
+}
+
+/// Long division from the lowest digit up, like
+/// [`DivisionPadicInteger`](crate::padic_div::DivisionPadicInteger) but without carries: quotient
+/// digit `n` is whatever makes coefficient `n` of `quotient * rhs` match `lhs`. Like there, the
+/// trailing zeros of `rhs` are only counted when a digit or the scale is asked for.
+pub(crate) struct CarryFreeDivisionPadicInteger<'a, Digit: Value> {
+    lhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
+    rhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
+    scale_adjustment: Cell<Option<usize>>,
+    cache: Cell<Vec<Digit>>,
+}
+
+impl<'a, Digit: Value> CarryFreeDivisionPadicInteger<'a, Digit> {
+    pub(crate) fn new(
+        lhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
+        rhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
+    ) -> CarryFreeDivisionPadicInteger<'a, Digit> {
+        CarryFreeDivisionPadicInteger {
+            lhs,
+            rhs,
+            scale_adjustment: Cell::new(None),
+            cache: Cell::new(vec![]),
+        }
+    }
+
+    fn scale_adjustment(&self) -> usize {
+        if let Some(zeros) = self.scale_adjustment.get() {
+            return zeros;
+        }
+        let zeros = count_low_zeros(self.rhs.as_ref());
+        self.scale_adjustment.set(Some(zeros));
+        zeros
+    }
+
+    fn get_rhs_digit(&self, index: usize) -> Digit {
+        self.rhs.get_integer_digit(index + self.scale_adjustment())
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for CarryFreeDivisionPadicInteger<'a, Digit> {
+    fn get_digit(&self, index: isize) -> Digit {
+        let Ok(adjusted_index) = usize::try_from(index + self.scale_adjustment() as isize) else {
+            return Digit::zero();
+        };
+
+        let mut cache = CellGuard::take(&self.cache);
+        let digit_cache = &mut cache.value;
+
+        if adjusted_index >= digit_cache.len() {
+            let rhs_inverse = self.get_rhs_digit(0).inverse();
+            for n in digit_cache.len()..=adjusted_index {
+                let known = digit_cache
+                    .iter()
+                    .enumerate()
+                    .fold(Digit::zero(), |sum, (k, &quotient_digit)| sum + quotient_digit * self.get_rhs_digit(n - k));
+                digit_cache.push((self.lhs.get_integer_digit(n) - known) * rhs_inverse);
+            }
+        }
+        digit_cache[adjusted_index]
+    }
+
+    fn get_scale(&self) -> isize {
+        -(self.scale_adjustment() as isize)
+    }
+}
+
+macro_rules! impl_laurent_operator {
+    ($trait:ident, $method:ident, |$lhs:ident, $rhs:ident| $body:expr) => {
+        impl<'a, Digit: Value + 'a> $trait for LaurentSeries<'a, Digit> {
+            type Output = LaurentSeries<'a, Digit>;
+
+            fn $method(self, rhs: Self) -> Self::Output {
+                let ($lhs, $rhs) = (self, rhs);
+                $body
+            }
+        }
+
+        impl<'a, Digit: Value + 'a> $trait for &LaurentSeries<'a, Digit> {
+            type Output = LaurentSeries<'a, Digit>;
+
+            fn $method(self, rhs: Self) -> Self::Output {
+                let ($lhs, $rhs) = (self.clone(), rhs.clone());
+                $body
+            }
+        }
+    };
+}
+
+impl_laurent_operator!(Add, add, |lhs, rhs| add(lhs, rhs, false));
+impl_laurent_operator!(Sub, sub, |lhs, rhs| add(lhs, rhs, true));
+impl_laurent_operator!(Mul, mul, |lhs, rhs| mul(lhs, rhs));
+impl_laurent_operator!(Div, div, |lhs, rhs| div(lhs, rhs));
+
+impl<'a, Digit: Value + 'a> Neg for LaurentSeries<'a, Digit> {
+    type Output = LaurentSeries<'a, Digit>;
+
+    fn neg(self) -> Self::Output {
+        let zero = LaurentSeries::from_digits(PadicNumber::from_exact(EventuallyPeriodicPadic::zero()));
+        add(zero, self, true)
+    }
+}
+
+impl<'a, Digit: Value + 'a> Neg for &LaurentSeries<'a, Digit> {
+    type Output = LaurentSeries<'a, Digit>;
+
+    fn neg(self) -> Self::Output {
+        -self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discrete::AddGroupU8;
+    use crate::padic_shift::ShiftedPadicNumber;
+
+    type Series<'a> = LaurentSeries<'a, AddGroupU8<3>>;
+
+    fn constant(value: u8) -> Series<'static> {
+        Series::from_digits(EventuallyPeriodicPadic::new(0, AddGroupU8::new_vec(vec![value]).unwrap(), vec![]).to_dyn())
+    }
+
+    fn coefficients(series: &Series, range: Range<isize>) -> Vec<u8> {
+        range.map(|i| series.digits().value.get_digit(i).value()).collect()
+    }
+
+    #[test]
+    #[should_panic(expected = "attempt to divide by zero")]
+    fn dividing_by_an_exact_zero_panics() {
+        let _ = constant(1) / constant(0);
+    }
+
+    #[test]
+    fn dividing_by_a_lazy_zero_builds_without_reading_it() {
+        let zero = Series::new(ShiftedPadicNumber::new(constant(0).digits().clone(), 0));
+        let _ = constant(1) / zero;
+    }
+
+    #[test]
+    fn division_undoes_multiplication() {
+        let lhs = constant(2) + constant(1).shift(3);
+        let rhs = constant(1) - constant(1).shift(1);
+        let quotient = &(&lhs * &rhs) / &rhs;
+        assert_eq!(coefficients(&quotient, -2..8), [0, 0, 2, 0, 0, 1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn negative_valuations() {
+        // t^-2 + 2 and t^-1 - 1
+        let lhs = constant(1).shift(-2) + constant(2);
+        let rhs = constant(1).shift(-1) - constant(1);
+        assert_eq!(lhs.valuation(10), Some(-2));
+        assert_eq!(coefficients(&(&lhs + &rhs), -3..3), [0, 1, 1, 1, 0, 0]);
+        // t^-3 - t^-2 + 2t^-1 - 2
+        let product = &lhs * &rhs;
+        assert_eq!(product.valuation(10), Some(-3));
+        assert_eq!(coefficients(&product, -4..3), [0, 1, 2, 2, 1, 0, 0]);
+        assert_eq!(coefficients(&(&product / &rhs), -4..3), [0, 0, 1, 0, 2, 0, 0]);
+        // t^-2 + 2 = t^-2 - 1 = (t^-1 + 1)(t^-1 - 1)
+        let quotient = &lhs / &rhs;
+        assert_eq!(quotient.valuation(10), Some(-1));
+        assert_eq!(coefficients(&quotient, -4..3), [0, 0, 0, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn divisor_with_zeros_below_its_first_coefficient() {
+        // t^2 + 2t^3, built so its scale is 0 with the zeros found by reading
+        let rhs = (constant(1) + constant(1).shift(2) + constant(2).shift(3)) - constant(1);
+        assert_eq!(rhs.get_scale(), 0);
+        let lhs = constant(2) + constant(1).shift(3);
+        // 2 + t^3 = (2t^-2 + 2t^-1 + 2)(t^2 + 2t^3)
+        let quotient = &lhs / &rhs;
+        assert_eq!(quotient.get_scale(), -2);
+        assert_eq!(coefficients(&quotient, -4..6), [0, 0, 2, 2, 2, 0, 0, 0, 0, 0]);
+        assert_eq!(coefficients(&(&quotient * &rhs), -4..6), [0, 0, 0, 0, 2, 0, 0, 1, 0, 0]);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::discrete::{AddGroupU8, Value};
+use crate::padic::PadicNumber;
+use std::fmt::{Debug, Display};
+use std::rc::Rc;
+
+/// How many digits are compared at a time.
+const BLOCK_SIZE: isize = 64;
+
+impl<'a, Digit: Value + 'a> PadicNumber<'a, Digit> {
+    /// The valuation of `self - other`, which is the lowest digit where they differ, or `None`
+    /// if they agree on every digit below `view_size`.
+    pub fn difference_valuation(&self, other: &Self, view_size: isize) -> Option<isize> {
+        if Rc::ptr_eq(&self.value, &other.value) || self.try_eq(other) == Some(true) {
+            return None;
+        }
+        let mut start = self.get_scale().min(other.get_scale());
+        let mut lhs = vec![Digit::zero(); BLOCK_SIZE as usize];
+        let mut rhs = vec![Digit::zero(); BLOCK_SIZE as usize];
+        while start < view_size {
+            let end = (start + BLOCK_SIZE).min(view_size);
+            let len = (end - start) as usize;
+            self.fill_digits(start..end, &mut lhs[..len]);
+            other.fill_digits(start..end, &mut rhs[..len]);
+            if let Some(offset) = (0..len).find(|&i| lhs[i] != rhs[i]) {
+                return Some(start + offset as isize);
+            }
+            start = end;
+        }
+        None
+    }
+
+    /// How many digits from 0 up `self` and `other` share, counting no further than `max`. It's
+    /// 0 when they differ after the point.
+    pub fn agreement(&self, other: &Self, max: usize) -> usize {
+        self.difference_valuation(other, max as isize).map_or(max, |valuation| valuation.max(0) as usize)
+    }
+
+    /// Whether `self` and `other` agree on every digit below `precision`, which means they're
+    /// equal mod p^precision.
+    pub fn approx_eq(&self, other: &Self, precision: isize) -> bool {
+        self.difference_valuation(other, precision).is_none()
+    }
+
+    /// Compares to `precision` digits, see [`Approximation`].
+    pub fn approx(&self, precision: isize) -> Approximation<'a, Digit> {
+        Approximation {
+            number: self.clone(),
+            precision,
+        }
+    }
+}
+
+impl<'a, const BASE: u8> PadicNumber<'a, AddGroupU8<BASE>> {
+    /// The p-adic distance `|self - other| = p^-v`, taken to be 0 when the numbers agree on every
+    /// digit below `view_size`.
+    pub fn distance(&self, other: &Self, view_size: isize) -> f64 {
+        self.difference_valuation(other, view_size)
+            .map_or(0.0, |valuation| (BASE as f64).powf(-valuation as f64))
+    }
+}
+
+/// A number that compares equal to anything agreeing with it below `precision` (the lower of the
+/// two precisions, when comparing two of these), and shows as the digits below it.
+///
+/// Comparing to a string compares to [`PadicNumber::as_view`] at that precision.
+///
+/// # Not an equivalence
+///
+/// `==` between approximations isn't transitive: `a.approx(1) == b.approx(5)` and
+/// `a.approx(1) == c.approx(5)` say nothing about `b.approx(5) == c.approx(5)`, since `b` and
+/// `c` only have to agree with `a` on digit 0. That's why this isn't `Eq`, and it shouldn't be
+/// used where equality has to be an equivalence, like deduplicating or grouping approximations.
+/// It's meant for asserting that a result matches an expected value to some precision.
+pub struct Approximation<'a, Digit: Value> {
+    number: PadicNumber<'a, Digit>,
+    precision: isize,
+}
+
+impl<'a, Digit: Value + 'a> PartialEq for Approximation<'a, Digit> {
+    fn eq(&self, other: &Self) -> bool {
+        self.number.approx_eq(&other.number, self.precision.min(other.precision))
+    }
+}
+
+impl<'a, Digit: Value + 'a> PartialEq<PadicNumber<'a, Digit>> for Approximation<'a, Digit> {
+    fn eq(&self, other: &PadicNumber<'a, Digit>) -> bool {
+        self.number.approx_eq(other, self.precision)
+    }
+}
+
+impl<'a, Digit: Value + 'a> PartialEq<&str> for Approximation<'a, Digit> {
+    fn eq(&self, other: &&str) -> bool {
+        self.to_string().as_str() == *other
+    }
+}
+
+impl<'a, Digit: Value + 'a> Display for Approximation<'a, Digit> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.number.as_view(self.precision), f)
+    }
+}
+
+impl<'a, Digit: Value + 'a> Debug for Approximation<'a, Digit> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::padic_test_util::{fraction, lazy, Exact};
+
+    #[test]
+    fn numbers_differing_at_a_digit() {
+        let a = lazy(&Exact::<3>::from_integer(10));
+        let b = lazy(&Exact::from_integer(10 + 81 * 5));
+        assert_eq!(a.difference_valuation(&b, 100), Some(4));
+        assert_eq!(a.difference_valuation(&b, 4), None);
+        assert_eq!(a.agreement(&b, 100), 4);
+        assert_eq!(a.agreement(&b, 3), 3);
+        assert!(a.approx_eq(&b, 4));
+        assert!(!a.approx_eq(&b, 5));
+        assert_eq!(a.distance(&b, 100), 1.0 / 81.0);
+        assert_eq!(a.distance(&a.clone(), 100), 0.0);
+    }
+
+    #[test]
+    fn numbers_differing_after_the_point() {
+        let third = fraction::<3>(1, 3);
+        assert_eq!(third.difference_valuation(&fraction(0, 1), 10), Some(-1));
+        assert_eq!(third.agreement(&fraction(0, 1), 10), 0);
+        assert_eq!(third.distance(&fraction(0, 1), 10), 3.0);
+    }
+
+    #[test]
+    fn differences_past_the_first_block() {
+        let far = lazy(&Exact::<3>::from_integer(1)) + lazy(&Exact::from_integer(3)).shift(300);
+        assert_eq!(far.difference_valuation(&fraction(1, 1), 1000), Some(301));
+        assert_eq!(far.difference_valuation(&fraction(1, 1), 301), None);
+    }
+
+    #[test]
+    fn approximations() {
+        let a = lazy(&Exact::<3>::from_integer(10));
+        let b = lazy(&Exact::from_integer(10 + 81 * 5));
+        assert_eq!(a.approx(4), b.approx(10));
+        assert_ne!(a.approx(5), b.approx(10));
+        assert!(a.approx(4) == b);
+        assert_eq!(a.approx(5), "00101");
+        assert_eq!((fraction::<3>(1, 3)).approx(2), "00.1");
+    }
+
+    #[test]
+    fn comparing_approximations_is_not_transitive() {
+        let (a, b, c) = (
+            lazy(&Exact::<3>::from_integer(1)),
+            lazy(&Exact::<3>::from_integer(1 + 3)),
+            lazy(&Exact::<3>::from_integer(1 + 2 * 3)),
+        );
+        assert_eq!(a.approx(1), b.approx(5));
+        assert_eq!(a.approx(1), c.approx(5));
+        assert_ne!(b.approx(5), c.approx(5));
+    }
+}
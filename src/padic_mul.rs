@@ -1,18 +1,21 @@
+use crate::digit_vec;
+use crate::discrete::Value;
+use crate::padic::{
+    checked_index, fill_scaled_digits, CellGuard, PadicAccessor, PadicIntegerAccessor, PadicNumber, PadicNumberScaler,
+};
+use crate::padic_nary::{ProductPadicNumber, TermList};
+use crate::padic_periodic::{EventuallyPeriodicPadic, EXACT_DIGIT_LIMIT};
 use std::cell::Cell;
 use std::collections::VecDeque;
-use crate::discrete::Value;
-use crate::padic::{PadicAccessor, PadicIntegerAccessor, PadicNumber, PadicNumberScaler};
 use std::ops::Mul;
+use std::ops::Range;
 use std::rc::Rc;
 
-impl<'a, Digit: Value + 'a> Mul for &'a PadicNumber<'a, Digit> {
+impl<'a, 'b, Digit: Value + 'a> Mul<&'b PadicNumber<'a, Digit>> for &'b PadicNumber<'a, Digit> {
     type Output = PadicNumber<'a, Digit>;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        PadicNumber::new_from_rc(Rc::new(MultiplicativePadicIntegerScaler::new(
-            self.clone(),
-            rhs.clone(),
-        )))
+        mul(self.clone(), rhs.clone())
     }
 }
 
@@ -20,22 +23,49 @@ impl<'a, Digit: Value + 'a> Mul for PadicNumber<'a, Digit> {
     type Output = PadicNumber<'a, Digit>;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        PadicNumber::new_from_rc(Rc::new(MultiplicativePadicIntegerScaler::new(
-            self, rhs,
-        )))
+        mul(self, rhs)
+    }
+}
+
+fn mul<'a, Digit: Value + 'a>(
+    lhs: PadicNumber<'a, Digit>,
+    rhs: PadicNumber<'a, Digit>,
+) -> PadicNumber<'a, Digit> {
+    match (lhs.exact(), rhs.exact()) {
+        (Some(zero), _) | (_, Some(zero)) if zero.is_zero() => {
+            return PadicNumber::from_exact(EventuallyPeriodicPadic::zero());
+        }
+        (Some(lhs), Some(rhs)) => {
+            if let Some(product) = lhs.checked_mul(rhs, EXACT_DIGIT_LIMIT) {
+                return PadicNumber::from_exact(product);
+            }
+        }
+        _ => {}
+    }
+    if !lhs.settle_scale() || !rhs.settle_scale() {
+        return PadicNumber::build_when_read(vec![lhs, rhs], |operands| mul(operands[0].clone(), operands[1].clone()));
+    }
+    if let Some(factors) = lhs.product_factors() {
+        return ProductPadicNumber::new(TermList::product_of(Some(factors), rhs)).to_dyn();
+    }
+    if let Some(factors) = rhs.product_factors() {
+        return ProductPadicNumber::new(TermList::product_of(Some(factors), lhs)).to_dyn();
     }
+    PadicNumber::new_from_rc(Rc::new(MultiplicativePadicIntegerScaler::new(lhs, rhs)))
 }
 
 pub struct MultiplicativePadicIntegerScaler<'a, Digit: Value> {
     inner: MultiplicationPadicInteger<'a, Digit>,
     scale: isize,
+    lhs: PadicNumber<'a, Digit>,
+    rhs: PadicNumber<'a, Digit>,
 }
 
 impl<'a, Digit: Value> MultiplicativePadicIntegerScaler<'a, Digit> {
     fn new(lhs: PadicNumber<'a, Digit>, rhs: PadicNumber<'a, Digit>) -> Self {
-        let scale = lhs.get_scale() + rhs.get_scale();
+        let scale = checked_index(lhs.get_scale().checked_add(rhs.get_scale()));
         let inner = MultiplicationPadicInteger::new(Rc::new(PadicNumberScaler::new(&lhs, lhs.get_scale())), Rc::new(PadicNumberScaler::new(&rhs, rhs.get_scale())));
-        MultiplicativePadicIntegerScaler { inner, scale }
+        MultiplicativePadicIntegerScaler { inner, scale, lhs, rhs }
     }
 }
 
@@ -49,15 +79,51 @@ impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for MultiplicativePadicInte
         }
     }
 
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        fill_scaled_digits(&self.inner, self.scale, range, digits);
+    }
+
     fn get_scale(&self) -> isize {
         self.scale
     }
+
+    fn product_factors(&self) -> Option<Rc<TermList<'a, Digit>>> {
+        Some(TermList::pair_product(&self.lhs, &self.rhs))
+    }
+
+    fn operands(&self) -> Vec<PadicNumber<'a, Digit>> {
+        vec![self.lhs.clone(), self.rhs.clone()]
+    }
+
+    fn take_operands(&mut self) -> Vec<PadicNumber<'a, Digit>> {
+        vec![self.lhs.take(), self.rhs.take()]
+    }
 }
 
+/// Multiplies two p-adic integers one digit at a time without giving up fast multiplication.
+///
+/// This is a relaxed (online) product: computing digit `n` only ever reads input digits up to
+/// `n`, but the partial products are grouped into square blocks whose sizes double, and each block
+/// is multiplied at once with [`digit_vec::mul`]. Getting `n` digits costs `O(M(n) log n)` digit
+/// operations, where `M(n)` is the cost of one Karatsuba product, instead of `O(n^2)`.
 pub(crate) struct MultiplicationPadicInteger<'a, Digit: Value> {
     lhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
     rhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
-    cache: Cell<(VecDeque<Digit>, VecDeque<Digit>)>,
+    cache: Cell<MultiplicationCache<Digit>>,
+}
+
+struct MultiplicationCache<Digit> {
+    lhs: Vec<Digit>,
+    rhs: Vec<Digit>,
+    computed: Vec<Digit>,
+    // block products that have been added but not yet emitted, starting at the next digit
+    carry: VecDeque<Digit>,
+}
+
+impl<Digit> Default for MultiplicationCache<Digit> {
+    fn default() -> Self {
+        MultiplicationCache { lhs: Vec::new(), rhs: Vec::new(), computed: Vec::new(), carry: VecDeque::new() }
+    }
 }
 
 impl<'a, Digit: Value> MultiplicationPadicInteger<'a, Digit> {
@@ -68,33 +134,55 @@ impl<'a, Digit: Value> MultiplicationPadicInteger<'a, Digit> {
         MultiplicationPadicInteger {
             lhs,
             rhs,
-            cache: Cell::new((VecDeque::new(), VecDeque::new())),
+            cache: Cell::new(MultiplicationCache::default()),
         }
     }
 }
 
 impl<'a, Digit: Value + 'a> PadicIntegerAccessor<'a, Digit> for MultiplicationPadicInteger<'a, Digit> {
     fn get_integer_digit(&self, index: usize) -> Digit {
-        let (mut computed, mut sum) = self.cache.take();
+        let mut digit = Digit::zero();
+        self.fill_integer_digits(index..index + 1, std::slice::from_mut(&mut digit));
+        digit
+    }
 
-        for index in computed.len()..=index {
-            for offset in 0..=index {
-                let mul_result = self.lhs.get_integer_digit(offset).mul_overflow(self.rhs.get_integer_digit(index - offset));
-                sum = add_vec(sum, VecDeque::from(vec![mul_result.0, mul_result.1]));
-            }
-            let digit = sum.pop_front().unwrap();
-            computed.push_back(digit);
-        }
+    fn fill_integer_digits(&self, range: Range<usize>, digits: &mut [Digit]) {
+        let mut cache = CellGuard::take(&self.cache);
+        let cache = &mut cache.value;
 
-        let result = computed[index];
+        let start = cache.computed.len();
+        if start < range.end {
+            cache.lhs.resize(range.end, Digit::zero());
+            cache.rhs.resize(range.end, Digit::zero());
+            self.lhs.fill_integer_digits(start..range.end, &mut cache.lhs[start..]);
+            self.rhs.fill_integer_digits(start..range.end, &mut cache.rhs[start..]);
+        }
+        for n in start..range.end {
+            // Every pair (i, j) of digit indices is covered by exactly one block: blocks of size
+            // `size` sit at [size - 1, 2 size - 1) in one operand and at a multiple of `size` in
+            // the other, and are added as soon as their last digit is known, which is always
+            // before column i + j is emitted.
+            let mut size = 1;
+            while (n + 2) % size == 0 && 2 * size <= n + 2 {
+                let start = n + 1 - size;
+                let product = digit_vec::mul(&cache.lhs[start..start + size], &cache.rhs[size - 1..2 * size - 1]);
+                cache.carry = add_vec(std::mem::take(&mut cache.carry), VecDeque::from(product));
+                if start != size - 1 {
+                    let product = digit_vec::mul(&cache.lhs[size - 1..2 * size - 1], &cache.rhs[start..start + size]);
+                    cache.carry = add_vec(std::mem::take(&mut cache.carry), VecDeque::from(product));
+                }
+                size *= 2;
+            }
 
-        self.cache.set((computed, sum));
+            let digit = cache.carry.pop_front().unwrap_or(Digit::zero());
+            cache.computed.push(digit);
+        }
 
-        result
+        digits.copy_from_slice(&cache.computed[range]);
     }
 }
 
-fn add_vec<Digit: Value>(lhs: VecDeque<Digit>, rhs: VecDeque<Digit>) -> VecDeque<Digit> {
+pub(crate) fn add_vec<Digit: Value>(lhs: VecDeque<Digit>, rhs: VecDeque<Digit>) -> VecDeque<Digit> {
     let mut index = 0;
     let mut carry = lhs;
     while let Some(&digit) = rhs.get(index) {
@@ -117,4 +205,39 @@ fn add_vec<Digit: Value>(lhs: VecDeque<Digit>, rhs: VecDeque<Digit>) -> VecDeque
         index += 1;
     }
     carry
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::padic::PadicNumber;
+    use crate::padic_test_util::{digits, lazy, operands, Exact};
+
+    fn check_products<const BASE: u8>(far: isize) {
+        for lhs in operands::<BASE>() {
+            for rhs in operands::<BASE>() {
+                let expected = PadicNumber::from_exact(&lhs * &rhs);
+                let in_order = lazy(&lhs) * lazy(&rhs);
+                assert!(in_order.exact().is_none());
+                assert_eq!(digits(&in_order, -8..far), digits(&expected, -8..far));
+                // one long block, past the Karatsuba threshold
+                let jumped = lazy(&lhs) * lazy(&rhs);
+                jumped.get_digit(far);
+                assert_eq!(digits(&jumped, -8..far), digits(&expected, -8..far));
+            }
+        }
+    }
+
+    #[test]
+    fn products_agree_with_exact_multiplication() {
+        check_products::<2>(1000);
+        check_products::<3>(1000);
+        check_products::<7>(1000);
+    }
+
+    #[test]
+    #[should_panic(expected = "a digit index overflows")]
+    fn lazy_products_past_isize_max_panic() {
+        let one = lazy(&Exact::<3>::one());
+        let _ = one.shift(isize::MAX) * one.shift(1);
+    }
+}
@@ -0,0 +1,476 @@
+use crate::digit_vec;
+use crate::discrete::Value;
+use crate::padic::{
+    checked_index, fill_scaled_digits, CellGuard, LeadingZeros, PadicAccessor, PadicIntegerAccessor, PadicNumber,
+    PadicNumberScaler,
+};
+use crate::padic_mul::MultiplicationPadicInteger;
+use crate::padic_periodic::{EventuallyPeriodicPadic, EXACT_DIGIT_LIMIT};
+use std::cell::{Cell, OnceCell};
+use std::iter::{Product, Sum};
+use std::ops::Range;
+use std::rc::Rc;
+
+/// The operands of a sum or product, newest first.
+///
+/// Lists are only ever extended at the front, so adding a term to a sum shares the rest of the
+/// list with it instead of copying it or nesting it. That keeps `acc = &acc + &x` in a loop from
+/// building a graph as deep as the loop is long.
+pub struct TermList<'a, Digit: Value> {
+    previous: Option<Rc<TermList<'a, Digit>>>,
+    /// Whether every term in `previous` is subtracted instead, so that `x - sum` can share the
+    /// terms of `sum` too.
+    previous_negated: bool,
+    term: PadicNumber<'a, Digit>,
+    negative: bool,
+    /// The scale of `term` when it was added, which later calls may have moved up since.
+    term_scale: isize,
+    len: usize,
+    /// The lowest scale of any term for sums, or the total scale of every factor for products.
+    scale: isize,
+}
+
+impl<'a, Digit: Value + 'a> TermList<'a, Digit> {
+    pub(crate) fn sum_of(
+        previous: Option<Rc<TermList<'a, Digit>>>,
+        term: PadicNumber<'a, Digit>,
+        negative: bool,
+    ) -> Rc<TermList<'a, Digit>> {
+        let scale = match &previous {
+            Some(previous) => previous.scale.min(term.get_scale()),
+            None => term.get_scale(),
+        };
+        Self::push(previous, false, term, negative, scale)
+    }
+
+    /// `term - previous`, which has the same terms as `previous`, subtracted.
+    pub(crate) fn difference_from(
+        term: PadicNumber<'a, Digit>,
+        previous: Rc<TermList<'a, Digit>>,
+    ) -> Rc<TermList<'a, Digit>> {
+        let scale = previous.scale.min(term.get_scale());
+        Self::push(Some(previous), true, term, false, scale)
+    }
+
+    pub(crate) fn product_of(
+        previous: Option<Rc<TermList<'a, Digit>>>,
+        factor: PadicNumber<'a, Digit>,
+    ) -> Rc<TermList<'a, Digit>> {
+        let previous_scale = previous.as_ref().map_or(0, |previous| previous.scale);
+        let scale = checked_index(previous_scale.checked_add(factor.get_scale()));
+        Self::push(previous, false, factor, false, scale)
+    }
+
+    /// The two operands of a binary sum or product, for nodes that only build their list when
+    /// something flattens them.
+    pub(crate) fn pair_sum(
+        lhs: &PadicNumber<'a, Digit>,
+        rhs: &PadicNumber<'a, Digit>,
+        negative: bool,
+    ) -> Rc<TermList<'a, Digit>> {
+        Self::sum_of(Some(Self::sum_of(None, lhs.clone(), false)), rhs.clone(), negative)
+    }
+
+    pub(crate) fn pair_product(lhs: &PadicNumber<'a, Digit>, rhs: &PadicNumber<'a, Digit>) -> Rc<TermList<'a, Digit>> {
+        Self::product_of(Some(Self::product_of(None, lhs.clone())), rhs.clone())
+    }
+
+    fn push(
+        previous: Option<Rc<TermList<'a, Digit>>>,
+        previous_negated: bool,
+        term: PadicNumber<'a, Digit>,
+        negative: bool,
+        scale: isize,
+    ) -> Rc<TermList<'a, Digit>> {
+        let len = previous.as_ref().map_or(0, |previous| previous.len) + 1;
+        let term_scale = term.get_scale();
+        Rc::new(TermList { previous, previous_negated, term, negative, term_scale, len, scale })
+    }
+
+    /// Every term with whether it's subtracted and its scale, without recursing down the list.
+    fn terms(&self) -> Vec<(PadicNumber<'a, Digit>, bool, isize)> {
+        let mut terms = Vec::with_capacity(self.len);
+        let mut list = Some(self);
+        let mut negated = false;
+        while let Some(node) = list {
+            terms.push((node.term.clone(), node.negative != negated, node.term_scale));
+            negated ^= node.previous_negated;
+            list = node.previous.as_deref();
+        }
+        terms
+    }
+
+    /// Moves the terms out of the part of `list` that nothing else shares, for
+    /// [`PadicAccessor::take_operands`].
+    fn take_terms(list: &mut Rc<Self>) -> Vec<PadicNumber<'a, Digit>> {
+        let mut terms = Vec::new();
+        let mut list = Rc::get_mut(list);
+        while let Some(node) = list {
+            terms.push(node.term.take());
+            list = node.previous.as_mut().and_then(Rc::get_mut);
+        }
+        terms
+    }
+}
+
+/// Long lists would otherwise be dropped recursively, one stack frame per term.
+impl<'a, Digit: Value> Drop for TermList<'a, Digit> {
+    fn drop(&mut self) {
+        let mut previous = self.previous.take();
+        while let Some(node) = previous {
+            match Rc::try_unwrap(node) {
+                Ok(mut node) => previous = node.previous.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+/// Exact terms are added up front, and everything else becomes one [`SumPadicNumber`].
+impl<'a, Digit: Value + 'a> Sum for PadicNumber<'a, Digit> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        let iter: Vec<Self> = iter.collect();
+        if !iter.iter().all(PadicNumber::settle_scale) {
+            return PadicNumber::build_when_read(iter, |terms| terms.iter().sum());
+        }
+        let mut exact = EventuallyPeriodicPadic::zero();
+        let mut terms = None;
+        for term in iter {
+            if let Some(sum) = term.exact().and_then(|term| exact.checked_add(term, EXACT_DIGIT_LIMIT)) {
+                exact = sum;
+            } else {
+                terms = Some(TermList::sum_of(terms, term, false));
+            }
+        }
+        match terms {
+            None => PadicNumber::from_exact(exact),
+            Some(terms) if exact.is_zero() && terms.len == 1 => terms.term.clone(),
+            Some(terms) if exact.is_zero() => SumPadicNumber::new(terms).to_dyn(),
+            Some(terms) => SumPadicNumber::new(TermList::sum_of(Some(terms), PadicNumber::from_exact(exact), false)).to_dyn(),
+        }
+    }
+}
+
+impl<'a, 'b, Digit: Value + 'a> Sum<&'b PadicNumber<'a, Digit>> for PadicNumber<'a, Digit> {
+    fn sum<I: Iterator<Item = &'b PadicNumber<'a, Digit>>>(iter: I) -> Self {
+        iter.cloned().sum()
+    }
+}
+
+/// Exact factors are multiplied up front, and everything else becomes one
+/// [`ProductPadicNumber`].
+impl<'a, Digit: Value + 'a> Product for PadicNumber<'a, Digit> {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        let iter: Vec<Self> = iter.collect();
+        if !iter.iter().all(PadicNumber::settle_scale) {
+            return PadicNumber::build_when_read(iter, |factors| factors.iter().product());
+        }
+        let mut exact = EventuallyPeriodicPadic::one();
+        let mut factors = None;
+        for factor in iter {
+            if let Some(product) = factor.exact().and_then(|factor| exact.checked_mul(factor, EXACT_DIGIT_LIMIT)) {
+                if product.is_zero() {
+                    return PadicNumber::from_exact(product);
+                }
+                exact = product;
+            } else {
+                factors = Some(TermList::product_of(factors, factor));
+            }
+        }
+        match factors {
+            None => PadicNumber::from_exact(exact),
+            Some(factors) if exact == EventuallyPeriodicPadic::one() && factors.len == 1 => factors.term.clone(),
+            Some(factors) if exact == EventuallyPeriodicPadic::one() => ProductPadicNumber::new(factors).to_dyn(),
+            Some(factors) => {
+                ProductPadicNumber::new(TermList::product_of(Some(factors), PadicNumber::from_exact(exact))).to_dyn()
+            }
+        }
+    }
+}
+
+impl<'a, 'b, Digit: Value + 'a> Product<&'b PadicNumber<'a, Digit>> for PadicNumber<'a, Digit> {
+    fn product<I: Iterator<Item = &'b PadicNumber<'a, Digit>>>(iter: I) -> Self {
+        iter.cloned().product()
+    }
+}
+
+/// Adds and subtracts any number of terms at once, a block of digits at a time.
+///
+/// The positive and negative terms are summed separately with a multi-digit carry each, and the
+/// two sums are only subtracted at the end of every block, so the carries stay non-negative.
+pub struct SumPadicNumber<'a, Digit: Value> {
+    terms: Rc<TermList<'a, Digit>>,
+    inner: OnceCell<SumPadicInteger<'a, Digit>>,
+}
+
+impl<'a, Digit: Value + 'a> SumPadicNumber<'a, Digit> {
+    pub(crate) fn new(terms: Rc<TermList<'a, Digit>>) -> Self {
+        SumPadicNumber { terms, inner: OnceCell::new() }
+    }
+
+    fn inner(&self) -> &SumPadicInteger<'a, Digit> {
+        self.inner.get_or_init(|| SumPadicInteger::new(self.terms.terms(), self.terms.scale))
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for SumPadicNumber<'a, Digit> {
+    fn get_digit(&self, index: isize) -> Digit {
+        let mut digit = Digit::zero();
+        self.fill_digits(index..index + 1, std::slice::from_mut(&mut digit));
+        digit
+    }
+
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        fill_scaled_digits(self.inner(), self.terms.scale, range, digits);
+    }
+
+    fn get_scale(&self) -> isize {
+        self.terms.scale + self.inner.get().map_or(0, |inner| inner.leading_zeros.get()) as isize
+    }
+
+    fn sum_terms(&self) -> Option<Rc<TermList<'a, Digit>>> {
+        Some(self.terms.clone())
+    }
+
+    fn operands(&self) -> Vec<PadicNumber<'a, Digit>> {
+        self.terms.terms().into_iter().map(|(term, _, _)| term).collect()
+    }
+
+    fn take_operands(&mut self) -> Vec<PadicNumber<'a, Digit>> {
+        TermList::take_terms(&mut self.terms)
+    }
+}
+
+struct SumPadicInteger<'a, Digit: Value> {
+    terms: Vec<(Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>, bool)>,
+    cache: Cell<SumCache<Digit>>,
+    leading_zeros: LeadingZeros,
+}
+
+struct SumCache<Digit> {
+    digits: Vec<Digit>,
+    positive_carry: Vec<Digit>,
+    negative_carry: Vec<Digit>,
+    borrow: bool,
+}
+
+impl<Digit> Default for SumCache<Digit> {
+    fn default() -> Self {
+        SumCache { digits: vec![], positive_carry: vec![], negative_carry: vec![], borrow: false }
+    }
+}
+
+impl<'a, Digit: Value + 'a> SumPadicInteger<'a, Digit> {
+    fn new(terms: Vec<(PadicNumber<'a, Digit>, bool, isize)>, scale: isize) -> Self {
+        let terms = terms
+            .into_iter()
+            .map(|(term, negative, _)| {
+                let term: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a> = Rc::new(PadicNumberScaler::new(&term, scale));
+                (term, negative)
+            })
+            .collect();
+        SumPadicInteger { terms, cache: Cell::new(SumCache::default()), leading_zeros: LeadingZeros::default() }
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicIntegerAccessor<'a, Digit> for SumPadicInteger<'a, Digit> {
+    fn get_integer_digit(&self, index: usize) -> Digit {
+        let mut digit = Digit::zero();
+        self.fill_integer_digits(index..index + 1, std::slice::from_mut(&mut digit));
+        digit
+    }
+
+    fn fill_integer_digits(&self, range: Range<usize>, digits: &mut [Digit]) {
+        let mut cache = CellGuard::take(&self.cache);
+        let cache = &mut cache.value;
+
+        let start = cache.digits.len();
+        if start < range.end {
+            let len = range.end - start;
+            let mut positive = cache.positive_carry.clone();
+            let mut negative = cache.negative_carry.clone();
+            let mut block = vec![Digit::zero(); len];
+            for (term, is_negative) in &self.terms {
+                term.fill_integer_digits(start..range.end, &mut block);
+                let total = if *is_negative { &mut negative } else { &mut positive };
+                digit_vec::add_shifted(total, &block, 0);
+            }
+            positive.resize(positive.len().max(len), Digit::zero());
+            negative.resize(negative.len().max(len), Digit::zero());
+            for (&positive_digit, &negative_digit) in positive[..len].iter().zip(&negative[..len]) {
+                let (digit_difference, digit_borrow) = positive_digit.sub_borrow(negative_digit);
+                let (digit, full_borrow) = digit_difference.sub_borrow(Digit::from_bool(cache.borrow));
+                cache.borrow = digit_borrow || full_borrow;
+                self.leading_zeros.push(cache.digits.len(), &digit);
+                cache.digits.push(digit);
+            }
+            cache.positive_carry = digit_vec::trim(positive.split_off(len));
+            cache.negative_carry = digit_vec::trim(negative.split_off(len));
+        }
+        digits.copy_from_slice(&cache.digits[range]);
+    }
+}
+
+/// Multiplies any number of factors through a balanced tree of products, so the graph only gets
+/// logarithmically deep in the number of factors.
+pub struct ProductPadicNumber<'a, Digit: Value> {
+    factors: Rc<TermList<'a, Digit>>,
+    inner: OnceCell<Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>>,
+}
+
+impl<'a, Digit: Value + 'a> ProductPadicNumber<'a, Digit> {
+    pub(crate) fn new(factors: Rc<TermList<'a, Digit>>) -> Self {
+        ProductPadicNumber { factors, inner: OnceCell::new() }
+    }
+
+    fn inner(&self) -> &Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a> {
+        self.inner.get_or_init(|| {
+            let mut level: Vec<Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>> = self
+                .factors
+                .terms()
+                .into_iter()
+                .map(|(factor, _, scale)| {
+                    let factor: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a> =
+                        Rc::new(PadicNumberScaler::new(&factor, scale));
+                    factor
+                })
+                .collect();
+            while level.len() > 1 {
+                let mut next: Vec<Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>> = Vec::with_capacity(level.len().div_ceil(2));
+                let mut factors = level.into_iter();
+                while let Some(lhs) = factors.next() {
+                    match factors.next() {
+                        Some(rhs) => next.push(Rc::new(MultiplicationPadicInteger::new(lhs, rhs))),
+                        None => next.push(lhs),
+                    }
+                }
+                level = next;
+            }
+            level.pop().unwrap()
+        })
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for ProductPadicNumber<'a, Digit> {
+    fn get_digit(&self, index: isize) -> Digit {
+        let adjusted_index = index - self.factors.scale;
+        if adjusted_index < 0 {
+            Digit::zero()
+        } else {
+            self.inner().get_integer_digit(adjusted_index as usize)
+        }
+    }
+
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        fill_scaled_digits(self.inner().as_ref(), self.factors.scale, range, digits);
+    }
+
+    fn get_scale(&self) -> isize {
+        self.factors.scale
+    }
+
+    fn product_factors(&self) -> Option<Rc<TermList<'a, Digit>>> {
+        Some(self.factors.clone())
+    }
+
+    fn operands(&self) -> Vec<PadicNumber<'a, Digit>> {
+        self.factors.terms().into_iter().map(|(term, _, _)| term).collect()
+    }
+
+    fn take_operands(&mut self) -> Vec<PadicNumber<'a, Digit>> {
+        TermList::take_terms(&mut self.factors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TermList;
+    use crate::discrete::AddGroupU8;
+    use crate::padic::PadicNumber;
+    use crate::padic_test_util::{digits, lazy, operands, Exact, Padic};
+    use std::rc::Rc;
+
+    fn exact_sum() -> Exact<3> {
+        operands::<3>().iter().fold(Exact::zero(), |sum, x| &sum + x)
+    }
+
+    fn exact_product() -> Exact<3> {
+        operands::<3>().iter().fold(Exact::one(), |product, x| &product * x)
+    }
+
+    /// Checks that `terms` has `len` terms and none of them is itself a sum or product.
+    fn assert_flat(terms: Option<Rc<TermList<AddGroupU8<3>>>>, len: usize) {
+        let terms = terms.expect("not an n-ary node");
+        assert_eq!(terms.len, len);
+        for (term, _, _) in terms.terms() {
+            assert!(term.sum_terms().is_none() && term.product_factors().is_none());
+        }
+    }
+
+    #[test]
+    fn empty_sum_and_product() {
+        let sum: Padic<3> = std::iter::empty::<Padic<3>>().sum();
+        assert_eq!(sum.exact(), Some(&Exact::zero()));
+        let product: Padic<3> = std::iter::empty::<Padic<3>>().product();
+        assert_eq!(product.exact(), Some(&Exact::one()));
+    }
+
+    #[test]
+    fn exact_sums_and_products_stay_exact() {
+        let terms: Vec<Padic<3>> = operands::<3>().into_iter().map(PadicNumber::from_exact).collect();
+        let sum: Padic<3> = terms.iter().sum();
+        assert_eq!(sum.exact(), Some(&exact_sum()));
+        let product: Padic<3> = terms.iter().product();
+        assert_eq!(product.exact(), Some(&exact_product()));
+    }
+
+    #[test]
+    fn lazy_sums_and_products_are_one_flat_node() {
+        let terms: Vec<Padic<3>> = operands::<3>().iter().map(lazy).collect();
+        let sum: Padic<3> = terms.iter().sum();
+        assert!(sum.exact().is_none());
+        assert_flat(sum.sum_terms(), terms.len());
+        assert_eq!(digits(&sum, -4..20), digits(&PadicNumber::from_exact(exact_sum()), -4..20));
+
+        let product: Padic<3> = terms.into_iter().product();
+        assert!(product.exact().is_none());
+        assert_flat(product.product_factors(), operands::<3>().len());
+        assert_eq!(digits(&product, -4..20), digits(&PadicNumber::from_exact(exact_product()), -4..20));
+    }
+
+    #[test]
+    fn mixed_terms_fold_the_exact_ones_into_one() {
+        let terms: Vec<Padic<3>> = operands::<3>()
+            .iter()
+            .enumerate()
+            .map(|(i, x)| if i % 2 == 1 { lazy(x) } else { PadicNumber::from_exact(x.clone()) })
+            .collect();
+        let lazy_terms = terms.iter().filter(|x| x.exact().is_none()).count();
+        let sum: Padic<3> = terms.iter().sum();
+        assert_flat(sum.sum_terms(), lazy_terms + 1);
+        assert_eq!(digits(&sum, -4..20), digits(&PadicNumber::from_exact(exact_sum()), -4..20));
+
+        let product: Padic<3> = terms.iter().product();
+        assert_flat(product.product_factors(), lazy_terms + 1);
+        assert_eq!(digits(&product, -4..20), digits(&PadicNumber::from_exact(exact_product()), -4..20));
+    }
+
+    #[test]
+    fn sums_and_products_built_by_reference_stay_flat() {
+        let x = lazy(&Exact::<3>::from_integer(2));
+        let mut sum = x.clone();
+        for _ in 0..100_000 {
+            sum = &sum + &x;
+        }
+        let expected = PadicNumber::from_exact(Exact::from_integer(200_002));
+        assert_eq!(digits(&sum, -4..16), digits(&expected, -4..16));
+
+        let minus_one = lazy(&Exact::<3>::from_integer(-1));
+        let mut product = minus_one.clone();
+        for _ in 0..20_000 {
+            product = &product * &minus_one;
+        }
+        let expected = PadicNumber::from_exact(Exact::from_integer(-1));
+        assert_eq!(digits(&product, -4..16), digits(&expected, -4..16));
+    }
+}
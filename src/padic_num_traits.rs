@@ -0,0 +1,254 @@
+//! `num-traits` implementations.
+//!
+//! `Num` needs `PartialEq`, which can't be decided for lazy numbers, so only
+//! [`EventuallyPeriodicPadic`] implements it. [`PadicNumber`] gets the arithmetic traits, which is
+//! enough for generic code that doesn't compare numbers; compare them with
+//! [`PadicNumber::try_eq`] or [`PadicNumber::approx_eq`].
+
+use crate::discrete::{self, AddGroupU8, Value};
+use crate::padic::{PadicError, PadicNumber};
+use crate::padic_periodic::EventuallyPeriodicPadic;
+use num_traits::{Inv, Num, One, Pow, Zero};
+use std::ops::Rem;
+
+impl<const BASE: u8> Zero for AddGroupU8<BASE> {
+    fn zero() -> Self {
+        discrete::Zero::zero()
+    }
+
+    fn is_zero(&self) -> bool {
+        discrete::Zero::is_zero(self)
+    }
+}
+
+impl<const BASE: u8> One for AddGroupU8<BASE> {
+    fn one() -> Self {
+        discrete::One::one()
+    }
+
+    fn is_one(&self) -> bool {
+        discrete::One::is_one(self)
+    }
+}
+
+impl<'a, Digit: Value + 'a> Zero for PadicNumber<'a, Digit> {
+    fn zero() -> Self {
+        PadicNumber::from_exact(EventuallyPeriodicPadic::zero())
+    }
+
+    /// Whether this is provably zero. That's only decided for exact numbers (see
+    /// [`PadicNumber::exact`]): a lazy number gives false even when all of its digits are zero,
+    /// like `x - 1` for a lazy `x` that is one.
+    fn is_zero(&self) -> bool {
+        self.exact().is_some_and(EventuallyPeriodicPadic::is_zero)
+    }
+}
+
+impl<'a, Digit: Value + 'a> One for PadicNumber<'a, Digit> {
+    fn one() -> Self {
+        PadicNumber::from_exact(EventuallyPeriodicPadic::one())
+    }
+}
+
+impl<Digit: Value> Zero for EventuallyPeriodicPadic<Digit> {
+    fn zero() -> Self {
+        EventuallyPeriodicPadic::zero()
+    }
+
+    fn is_zero(&self) -> bool {
+        EventuallyPeriodicPadic::is_zero(self)
+    }
+}
+
+impl<Digit: Value> One for EventuallyPeriodicPadic<Digit> {
+    fn one() -> Self {
+        EventuallyPeriodicPadic::one()
+    }
+}
+
+/// Rationals are a field, so dividing never leaves a remainder.
+impl<Digit: Value> Rem for EventuallyPeriodicPadic<Digit> {
+    type Output = EventuallyPeriodicPadic<Digit>;
+
+    fn rem(self, rhs: Self) -> Self::Output {
+        assert!(!rhs.is_zero(), "attempt to calculate the remainder with a divisor of zero");
+        EventuallyPeriodicPadic::zero()
+    }
+}
+
+impl<Digit: Value> Num for EventuallyPeriodicPadic<Digit> {
+    type FromStrRadixErr = PadicError;
+
+    /// Reads an integer, with an optional sign, written in `radix` (which doesn't have to be p).
+    fn from_str_radix(string: &str, radix: u32) -> Result<Self, PadicError> {
+        let (negative, digits) = match string.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, string.strip_prefix('+').unwrap_or(string)),
+        };
+        if digits.is_empty() {
+            return Err(PadicError::InvalidDigit);
+        }
+        let radix_value = EventuallyPeriodicPadic::from_natural(radix as u64);
+        let mut value = EventuallyPeriodicPadic::zero();
+        for char in digits.chars() {
+            let digit = char.to_digit(radix).ok_or(PadicError::InvalidDigit)?;
+            value = &(&value * &radix_value) + &EventuallyPeriodicPadic::from_natural(digit as u64);
+        }
+        Ok(if negative { -value } else { value })
+    }
+}
+
+impl<'a, Digit: Value + 'a> Inv for PadicNumber<'a, Digit> {
+    type Output = PadicNumber<'a, Digit>;
+
+    fn inv(self) -> Self::Output {
+        self.reciprocal()
+    }
+}
+
+impl<'a, Digit: Value + 'a> Inv for &PadicNumber<'a, Digit> {
+    type Output = PadicNumber<'a, Digit>;
+
+    fn inv(self) -> Self::Output {
+        self.reciprocal()
+    }
+}
+
+/// Squares and multiplies, so the product has about `2 log2(exponent)` factors.
+fn pow<'a, Digit: Value + 'a>(base: &PadicNumber<'a, Digit>, exponent: u64) -> PadicNumber<'a, Digit> {
+    let mut result = PadicNumber::one();
+    for bit in (0..u64::BITS - exponent.leading_zeros()).rev() {
+        result = &result * &result;
+        if (exponent >> bit) & 1 == 1 {
+            result *= base;
+        }
+    }
+    result
+}
+
+macro_rules! impl_pow {
+    ($($exponent:ty),*) => {$(
+        impl<'a, Digit: Value + 'a> Pow<$exponent> for PadicNumber<'a, Digit> {
+            type Output = PadicNumber<'a, Digit>;
+
+            fn pow(self, exponent: $exponent) -> Self::Output {
+                let power = pow(&self, exponent.unsigned_abs() as u64);
+                if exponent < 0 { power.reciprocal() } else { power }
+            }
+        }
+
+        impl<'a, Digit: Value + 'a> Pow<$exponent> for &PadicNumber<'a, Digit> {
+            type Output = PadicNumber<'a, Digit>;
+
+            fn pow(self, exponent: $exponent) -> Self::Output {
+                self.clone().pow(exponent)
+            }
+        }
+    )*};
+}
+
+impl_pow!(i32, i64);
+
+macro_rules! impl_unsigned_pow {
+    ($($exponent:ty),*) => {$(
+        impl<'a, Digit: Value + 'a> Pow<$exponent> for PadicNumber<'a, Digit> {
+            type Output = PadicNumber<'a, Digit>;
+
+            fn pow(self, exponent: $exponent) -> Self::Output {
+                pow(&self, exponent as u64)
+            }
+        }
+
+        impl<'a, Digit: Value + 'a> Pow<$exponent> for &PadicNumber<'a, Digit> {
+            type Output = PadicNumber<'a, Digit>;
+
+            fn pow(self, exponent: $exponent) -> Self::Output {
+                pow(self, exponent as u64)
+            }
+        }
+    )*};
+}
+
+impl_unsigned_pow!(u32, u64, usize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::padic_test_util::{fraction, lazy, Exact, Padic};
+    use std::ops::Mul;
+
+    fn exact_value(number: &Padic<'static, 5>) -> Exact<5> {
+        number.exact().cloned().unwrap()
+    }
+
+    /// Generic code only sees the traits.
+    fn sum_of_squares<T: Zero + Mul<Output = T> + Clone>(values: &[T]) -> T {
+        values.iter().fold(T::zero(), |acc, value| acc + value.clone() * value.clone())
+    }
+
+    fn remainder<T: Num>(lhs: T, rhs: T) -> T {
+        lhs % rhs
+    }
+
+    #[test]
+    fn zero_and_one() {
+        assert!(<AddGroupU8<5> as Zero>::is_zero(&Zero::zero()));
+        assert!(<AddGroupU8<5> as One>::is_one(&One::one()));
+        assert!(Padic::<5>::zero().is_zero());
+        assert!(!Padic::<5>::one().is_zero());
+        assert_eq!(Padic::<5>::one().exact(), Some(&Exact::one()));
+        // all zero digits, but lazy
+        let x = lazy(&Exact::<5>::one());
+        assert!(!(&x - &Padic::one()).is_zero());
+        assert!(Exact::<5>::zero().is_zero());
+        assert!(Exact::<5>::one().is_one());
+    }
+
+    #[test]
+    fn num_in_generic_code() {
+        let values: Vec<Exact<5>> = [fraction(1, 2), fraction(-2, 3), fraction(3, 1)].iter().map(exact_value).collect();
+        let expected = exact_value(&(fraction(1, 4) + fraction(4, 9) + fraction(9, 1)));
+        assert_eq!(sum_of_squares(&values), expected);
+        assert_eq!(remainder(values[0].clone(), values[1].clone()), Exact::zero());
+        // lazy numbers work in generic code that doesn't compare them
+        let numbers: Vec<Padic<5>> = values.iter().map(lazy).collect();
+        assert!(sum_of_squares(&numbers).approx_eq(&PadicNumber::from_exact(expected), 40));
+    }
+
+    #[test]
+    fn from_str_radix() {
+        assert_eq!(Exact::<5>::from_str_radix("-123", 10), Ok(Exact::from_integer(-123)));
+        assert_eq!(Exact::<5>::from_str_radix("ff", 16), Ok(Exact::from_integer(255)));
+        assert_eq!(Exact::<5>::from_str_radix("12", 2), Err(PadicError::InvalidDigit));
+        assert_eq!(Exact::<5>::from_str_radix("-", 10), Err(PadicError::InvalidDigit));
+    }
+
+    #[test]
+    fn inv_and_pow() {
+        let two_thirds = fraction::<5>(2, 3);
+        assert_eq!(exact_value(&(&two_thirds).inv()), exact_value(&fraction(3, 2)));
+        assert_eq!(exact_value(&two_thirds.clone().pow(3u32)), exact_value(&fraction(8, 27)));
+        assert_eq!(exact_value(&(&two_thirds).pow(0usize)), Exact::one());
+        assert_eq!(exact_value(&two_thirds.clone().pow(-2i32)), exact_value(&fraction(9, 4)));
+        assert_eq!(exact_value(&(&two_thirds).pow(-3i64)), exact_value(&fraction(27, 8)));
+        // the same through lazy nodes
+        let lazy_two_thirds = lazy(&exact_value(&two_thirds));
+        let inverse = fraction(3, 2);
+        assert!(lazy_two_thirds.clone().inv().approx_eq(&inverse, 40));
+        let power = fraction(243, 32);
+        assert!(lazy_two_thirds.clone().pow(-5i32).approx_eq(&power, 40));
+        assert!(lazy_two_thirds.pow(5u64).approx_eq(&power.inv(), 40));
+    }
+
+    #[test]
+    #[should_panic(expected = "attempt to divide by zero")]
+    fn inv_of_zero_panics() {
+        Padic::<5>::zero().inv();
+    }
+
+    #[test]
+    #[should_panic(expected = "attempt to divide by zero")]
+    fn negative_powers_of_zero_panic() {
+        Padic::<5>::zero().pow(-1i32);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::discrete::Value;
+use crate::padic::PadicNumber;
+use crate::padic_periodic::EventuallyPeriodicPadic;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
+
+// Everything here converts its operands and forwards to `PadicNumber op PadicNumber`, so scalars
+// and mixed references get the same exact arithmetic and flattening as two numbers do.
+
+macro_rules! impl_mixed_operator {
+    ($trait:ident, $method:ident, $assign_trait:ident, $assign_method:ident) => {
+        impl<'a, 'b, Digit: Value + 'a> $trait<PadicNumber<'a, Digit>> for &'b PadicNumber<'a, Digit> {
+            type Output = PadicNumber<'a, Digit>;
+
+            fn $method(self, rhs: PadicNumber<'a, Digit>) -> Self::Output {
+                self.clone().$method(rhs)
+            }
+        }
+
+        impl<'a, 'b, Digit: Value + 'a> $trait<&'b PadicNumber<'a, Digit>> for PadicNumber<'a, Digit> {
+            type Output = PadicNumber<'a, Digit>;
+
+            fn $method(self, rhs: &'b PadicNumber<'a, Digit>) -> Self::Output {
+                self.$method(rhs.clone())
+            }
+        }
+
+        impl<'a, Digit: Value + 'a, Rhs> $assign_trait<Rhs> for PadicNumber<'a, Digit>
+        where
+            PadicNumber<'a, Digit>: $trait<Rhs, Output = PadicNumber<'a, Digit>>,
+        {
+            fn $assign_method(&mut self, rhs: Rhs) {
+                // moved out rather than cloned, since `self` is overwritten anyway
+                let lhs = std::mem::replace(self, PadicNumber::from_exact(EventuallyPeriodicPadic::zero()));
+                *self = lhs.$method(rhs);
+            }
+        }
+
+        impl_scalar_operator!($trait, $method, [], u64, |n| EventuallyPeriodicPadic::from_natural(n));
+        impl_scalar_operator!($trait, $method, [], i64, |n| EventuallyPeriodicPadic::from_integer(n));
+        impl_scalar_operator!($trait, $method, [], EventuallyPeriodicPadic<Digit>, |n| n);
+        impl_scalar_operator!($trait, $method, ['b], &'b EventuallyPeriodicPadic<Digit>, |n| n.clone());
+    };
+}
+
+/// A scalar on either side of a number or a reference to one. The lifetimes are the ones the
+/// scalar type uses.
+macro_rules! impl_scalar_operator {
+    ($trait:ident, $method:ident, [$($lifetime:lifetime),*], $scalar:ty, |$n:ident| $exact:expr) => {
+        impl<'a, $($lifetime,)* Digit: Value + 'a> $trait<$scalar> for PadicNumber<'a, Digit> {
+            type Output = PadicNumber<'a, Digit>;
+
+            fn $method(self, $n: $scalar) -> Self::Output {
+                self.$method(PadicNumber::from_exact($exact))
+            }
+        }
+
+        impl<'a, $($lifetime,)* 'c, Digit: Value + 'a> $trait<$scalar> for &'c PadicNumber<'a, Digit> {
+            type Output = PadicNumber<'a, Digit>;
+
+            fn $method(self, $n: $scalar) -> Self::Output {
+                self.clone().$method(PadicNumber::from_exact($exact))
+            }
+        }
+
+        impl<'a, $($lifetime,)* Digit: Value + 'a> $trait<PadicNumber<'a, Digit>> for $scalar {
+            type Output = PadicNumber<'a, Digit>;
+
+            fn $method(self, rhs: PadicNumber<'a, Digit>) -> Self::Output {
+                let $n = self;
+                PadicNumber::from_exact($exact).$method(rhs)
+            }
+        }
+
+        impl<'a, $($lifetime,)* 'c, Digit: Value + 'a> $trait<&'c PadicNumber<'a, Digit>> for $scalar {
+            type Output = PadicNumber<'a, Digit>;
+
+            fn $method(self, rhs: &'c PadicNumber<'a, Digit>) -> Self::Output {
+                let $n = self;
+                PadicNumber::from_exact($exact).$method(rhs.clone())
+            }
+        }
+    };
+}
+
+impl_mixed_operator!(Add, add, AddAssign, add_assign);
+impl_mixed_operator!(Sub, sub, SubAssign, sub_assign);
+impl_mixed_operator!(Mul, mul, MulAssign, mul_assign);
+impl_mixed_operator!(Div, div, DivAssign, div_assign);
+
+#[cfg(test)]
+mod tests {
+    use crate::padic::PadicNumber;
+    use crate::padic_test_util::{digits, lazy, Exact, Padic};
+
+    fn exact(numerator: i64, denominator: i64) -> Exact<3> {
+        &Exact::from_integer(numerator) / &Exact::from_integer(denominator)
+    }
+
+    fn assert_digits(x: &Padic<3>, expected: Exact<3>) {
+        assert_eq!(digits(x, -8..20), digits(&PadicNumber::from_exact(expected), -8..20));
+    }
+
+    /// Every form of `$op` and `$assign` on numbers, references and scalars, checked against the
+    /// exact result with the operands in the same order.
+    macro_rules! check_operator {
+        ($op:tt, $assign:tt) => {{
+            let (x, y) = (exact(7, 3), exact(-5, 12));
+            let (a, b) = (lazy(&x), lazy(&y));
+            assert_digits(&(&a $op b.clone()), &x $op &y);
+            assert_digits(&(a.clone() $op &b), &x $op &y);
+
+            let n = -4i64;
+            assert_digits(&(a.clone() $op n), &x $op &Exact::from_integer(n));
+            assert_digits(&(&a $op n), &x $op &Exact::from_integer(n));
+            assert_digits(&(n $op a.clone()), &Exact::from_integer(n) $op &x);
+            assert_digits(&(n $op &a), &Exact::from_integer(n) $op &x);
+
+            let u = 5u64;
+            assert_digits(&(a.clone() $op u), &x $op &Exact::from_natural(u));
+            assert_digits(&(&a $op u), &x $op &Exact::from_natural(u));
+            assert_digits(&(u $op a.clone()), &Exact::from_natural(u) $op &x);
+            assert_digits(&(u $op &a), &Exact::from_natural(u) $op &x);
+
+            assert_digits(&(a.clone() $op y.clone()), &x $op &y);
+            assert_digits(&(&a $op &y), &x $op &y);
+            assert_digits(&(y.clone() $op a.clone()), &y $op &x);
+            assert_digits(&(&y $op &a), &y $op &x);
+
+            let mut c = a.clone();
+            c $assign b.clone();
+            assert_digits(&c, &x $op &y);
+            c $assign &b;
+            assert_digits(&c, &(&x $op &y) $op &y);
+            c $assign n;
+            assert_digits(&c, &(&(&x $op &y) $op &y) $op &Exact::from_integer(n));
+        }};
+    }
+
+    #[test]
+    fn addition() {
+        check_operator!(+, +=);
+    }
+
+    #[test]
+    fn subtraction() {
+        check_operator!(-, -=);
+    }
+
+    #[test]
+    fn multiplication() {
+        check_operator!(*, *=);
+    }
+
+    #[test]
+    fn division() {
+        check_operator!(/, /=);
+    }
+}
@@ -0,0 +1,605 @@
+use crate::digit_vec;
+use crate::discrete::Value;
+use crate::padic::{checked_index, PadicAccessor, PadicNumber};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// How many digits of an exact result of `PadicNumber` arithmetic get worked out (see
+/// [`EventuallyPeriodicPadic::checked_add`]) before it falls back to lazy evaluation. The
+/// operators on [`EventuallyPeriodicPadic`] itself have no limit.
+pub const EXACT_DIGIT_LIMIT: usize = 1024;
+
+/// An exact p-adic number whose digits are eventually periodic, which are exactly the rationals.
+///
+/// Starting at index `scale`, the digits are `pre_period` followed by `period` repeated forever
+/// (both little-endian). Values are always kept canonical: the period is as short as possible,
+/// the pre-period can't be folded into the period, and the lowest digit is nonzero (zero itself
+/// is an empty pre-period with a period of `0` at scale 0). This means `==` is exact equality.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventuallyPeriodicPadic<Digit: Value> {
+    scale: isize,
+    pre_period: Vec<Digit>,
+    period: Vec<Digit>,
+}
+
+impl<Digit: Value> EventuallyPeriodicPadic<Digit> {
+    /// Builds the number whose digits, starting at index `scale`, are `pre_period` followed by
+    /// `period` repeated forever (both little-endian). An empty period means the number ends
+    /// after the pre-period.
+    pub fn new(scale: isize, pre_period: Vec<Digit>, period: Vec<Digit>) -> Self {
+        let mut period = if period.is_empty() { vec![Digit::zero()] } else { period };
+
+        let len = period.len();
+        if let Some(shortest) = (1..len)
+            .filter(|&d| len.is_multiple_of(d))
+            .find(|&d| (d..len).all(|i| period[i] == period[i - d]))
+        {
+            period.truncate(shortest);
+        }
+
+        let mut pre_period = pre_period;
+        while pre_period.last().is_some_and(|digit| digit == period.last().unwrap()) {
+            pre_period.pop();
+            period.rotate_right(1);
+        }
+
+        if pre_period.is_empty() && period.iter().all(|digit| digit.is_zero()) {
+            return Self::zero();
+        }
+
+        let mut scale = scale;
+        let leading_zeros = pre_period.iter().take_while(|digit| digit.is_zero()).count();
+        pre_period.drain(..leading_zeros);
+        scale += leading_zeros as isize;
+        if pre_period.is_empty() {
+            while period[0].is_zero() {
+                period.rotate_left(1);
+                scale += 1;
+            }
+        }
+
+        EventuallyPeriodicPadic {
+            scale,
+            pre_period,
+            period,
+        }
+    }
+
+    pub fn zero() -> Self {
+        EventuallyPeriodicPadic {
+            scale: 0,
+            pre_period: vec![],
+            period: vec![Digit::zero()],
+        }
+    }
+
+    pub fn one() -> Self {
+        EventuallyPeriodicPadic {
+            scale: 0,
+            pre_period: vec![Digit::one()],
+            period: vec![Digit::zero()],
+        }
+    }
+
+    /// `n` as a p-adic integer, built by doubling and adding so it works in any base.
+    pub fn from_natural(n: u64) -> Self {
+        let mut result = Self::zero();
+        for bit in (0..u64::BITS - n.leading_zeros()).rev() {
+            result = &result + &result;
+            if (n >> bit) & 1 == 1 {
+                result = &result + &Self::one();
+            }
+        }
+        result
+    }
+
+    /// Like [`Self::from_natural`], for any sign.
+    pub fn from_integer(n: i64) -> Self {
+        let magnitude = Self::from_natural(n.unsigned_abs());
+        if n < 0 { -magnitude } else { magnitude }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.pre_period.is_empty() && self.period[0].is_zero()
+    }
+
+    /// The index of the lowest nonzero digit (0 for zero itself).
+    pub fn scale(&self) -> isize {
+        self.scale
+    }
+
+    pub fn pre_period(&self) -> &[Digit] {
+        &self.pre_period
+    }
+
+    pub fn period(&self) -> &[Digit] {
+        &self.period
+    }
+
+    /// Multiplies by `p^k`. Panics with [`crate::padic::PadicError::ScaleOverflow`] if the
+    /// scale doesn't fit in an `isize`.
+    pub fn shift(&self, k: isize) -> Self {
+        if self.is_zero() {
+            Self::zero()
+        } else {
+            EventuallyPeriodicPadic {
+                scale: checked_index(self.scale.checked_add(k)),
+                ..self.clone()
+            }
+        }
+    }
+
+    /// Adds, or gives up once more than `max_digits` digits of the result have been worked out.
+    ///
+    /// Digits are worked out until they start repeating, so a result that comes back has at most
+    /// `max_digits` digits (pre-period plus period). The limit bounds that work rather than the
+    /// result, though: when the periods of the operands don't line up, the digits can settle
+    /// into a shorter period than it takes to find, so a result that would fit may be refused.
+    ///
+    /// This and the other `checked_*` methods only check that limit. A result whose scale
+    /// doesn't fit in an `isize` panics with [`crate::padic::PadicError::ScaleOverflow`], like
+    /// [`Self::shift`] and the lazy nodes do.
+    pub fn checked_add(&self, rhs: &Self, max_digits: usize) -> Option<Self> {
+        self.combine(rhs, add_digits, max_digits)
+    }
+
+    /// Subtracts, with the same limit as [`Self::checked_add`].
+    pub fn checked_sub(&self, rhs: &Self, max_digits: usize) -> Option<Self> {
+        self.combine(rhs, sub_digits, max_digits)
+    }
+
+    /// Multiplies, with the same limit as [`Self::checked_add`].
+    pub fn checked_mul(&self, rhs: &Self, max_digits: usize) -> Option<Self> {
+        if self.is_zero() || rhs.is_zero() {
+            return Some(Self::zero());
+        }
+        let (lhs_negative, lhs_numerator, lhs_denominator) = self.to_fraction();
+        let (rhs_negative, rhs_numerator, rhs_denominator) = rhs.to_fraction();
+        Self::from_fraction(
+            checked_index(self.scale.checked_add(rhs.scale)),
+            lhs_negative != rhs_negative,
+            &digit_vec::mul(&lhs_numerator, &rhs_numerator),
+            &digit_vec::mul(&lhs_denominator, &rhs_denominator),
+            max_digits,
+        )
+    }
+
+    /// Divides, unless `rhs` is zero, with the same limit as [`Self::checked_add`].
+    pub fn checked_div(&self, rhs: &Self, max_digits: usize) -> Option<Self> {
+        if rhs.is_zero() {
+            return None;
+        }
+        if self.is_zero() {
+            return Some(Self::zero());
+        }
+        let (lhs_negative, lhs_numerator, lhs_denominator) = self.to_fraction();
+        let (rhs_negative, rhs_numerator, rhs_denominator) = rhs.to_fraction();
+        Self::from_fraction(
+            checked_index(self.scale.checked_sub(rhs.scale)),
+            lhs_negative != rhs_negative,
+            &digit_vec::mul(&lhs_numerator, &rhs_denominator),
+            &digit_vec::mul(&lhs_denominator, &rhs_numerator),
+            max_digits,
+        )
+    }
+
+    /// The digit `index` places above the scale.
+    fn unit_digit(&self, index: usize) -> Digit {
+        if index < self.pre_period.len() {
+            self.pre_period[index]
+        } else {
+            self.period[(index - self.pre_period.len()) % self.period.len()]
+        }
+    }
+
+    /// Writes the digits above the scale as `sign * numerator / denominator`, where
+    /// `denominator` is `p^L - 1` for a period of length `L`.
+    fn to_fraction(&self) -> (bool, Vec<Digit>, Vec<Digit>) {
+        // pre_period + p^m * period / (1 - p^L) = (pre_period * (p^L - 1) - p^m * period) / (p^L - 1)
+        let max_digit = Digit::zero().sub_borrow(Digit::one()).0;
+        let denominator = vec![max_digit; self.period.len()];
+        let lhs = digit_vec::mul(&self.pre_period, &denominator);
+        let mut rhs = vec![Digit::zero(); self.pre_period.len()];
+        rhs.extend_from_slice(&self.period);
+        let rhs = digit_vec::trim(rhs);
+        match digit_vec::cmp(&lhs, &rhs) {
+            Ordering::Less => (true, digit_vec::sub(&rhs, &lhs), denominator),
+            _ => (false, digit_vec::sub(&lhs, &rhs), denominator),
+        }
+    }
+
+    /// Expands `p^scale * sign * numerator / denominator`, where `denominator` isn't divisible by
+    /// p.
+    fn from_fraction(
+        scale: isize,
+        negative: bool,
+        numerator: &[Digit],
+        denominator: &[Digit],
+        limit: usize,
+    ) -> Option<Self> {
+        let numerator = Self::new(0, numerator.to_vec(), vec![]);
+        let numerator = if negative { -numerator } else { numerator };
+        if numerator.is_zero() {
+            return Some(numerator);
+        }
+
+        let denominator_inverse = denominator[0].inverse();
+        // Long division from the bottom: `remainder` is always less than the denominator
+        let (pre_period, period) = transduce(
+            numerator.pre_period.len(),
+            numerator.period.len(),
+            limit,
+            Vec::<Digit>::new(),
+            |remainder, index| {
+                let digit = numerator.unit_digit(index);
+                let low_digit = remainder.first().copied().unwrap_or(Digit::zero());
+                let quotient_digit = digit.sub_borrow(low_digit).0 * denominator_inverse;
+                let total = digit_vec::add(remainder, &digit_vec::mul_digit(denominator, quotient_digit));
+                let mut total = digit_vec::sub(&total, &[digit]);
+                if !total.is_empty() {
+                    debug_assert!(total[0].is_zero());
+                    total.remove(0);
+                }
+                *remainder = total;
+                quotient_digit
+            },
+        )?;
+        Some(Self::new(checked_index(scale.checked_add(numerator.scale)), pre_period, period))
+    }
+
+    /// Adds or subtracts digit by digit, threading a carry through `op`.
+    fn combine(
+        &self,
+        rhs: &Self,
+        op: fn(Digit, Digit, bool) -> (Digit, bool),
+        limit: usize,
+    ) -> Option<Self> {
+        let scale = self.scale.min(rhs.scale);
+        let lhs_offset = self.scale.abs_diff(scale);
+        let rhs_offset = rhs.scale.abs_diff(scale);
+        // the zeros between the two scales are all digits of the result
+        if lhs_offset.max(rhs_offset) > limit {
+            return None;
+        }
+        let digit = |number: &Self, offset: usize, index: usize| {
+            if index < offset {
+                Digit::zero()
+            } else {
+                number.unit_digit(index - offset)
+            }
+        };
+        let (pre_period, period) = transduce(
+            (lhs_offset + self.pre_period.len()).max(rhs_offset + rhs.pre_period.len()),
+            lcm(self.period.len(), rhs.period.len()),
+            limit,
+            false,
+            |carry, index| {
+                let (result, next_carry) = op(
+                    digit(self, lhs_offset, index),
+                    digit(rhs, rhs_offset, index),
+                    *carry,
+                );
+                *carry = next_carry;
+                result
+            },
+        )?;
+        Some(Self::new(scale, pre_period, period))
+    }
+}
+
+/// Runs `step` over an input that is eventually periodic (with the given pre-period and period
+/// lengths), and returns the pre-period and period of the output. `step` gets the input index
+/// and may only depend on that and `state`, so the output starts repeating as soon as `state`
+/// repeats at the start of an input period. Gives up instead of computing more than `limit`
+/// output digits.
+fn transduce<Digit: Value, State: Ord + Clone>(
+    pre_period_len: usize,
+    period_len: usize,
+    limit: usize,
+    mut state: State,
+    mut step: impl FnMut(&mut State, usize) -> Digit,
+) -> Option<(Vec<Digit>, Vec<Digit>)> {
+    let mut output = Vec::new();
+    let mut index = 0;
+    let mut next_digit = |state: &mut State, output: &mut Vec<Digit>| {
+        if output.len() >= limit {
+            return None;
+        }
+        output.push(step(state, index));
+        index += 1;
+        Some(())
+    };
+    for _ in 0..pre_period_len {
+        next_digit(&mut state, &mut output)?;
+    }
+    let mut seen = BTreeMap::new();
+    loop {
+        if let Some(&start) = seen.get(&state) {
+            let period = output.split_off(start);
+            return Some((output, period));
+        }
+        seen.insert(state.clone(), output.len());
+        for _ in 0..period_len {
+            next_digit(&mut state, &mut output)?;
+        }
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn lcm(a: usize, b: usize) -> usize {
+    a / gcd(a, b) * b
+}
+
+fn add_digits<Digit: Value>(lhs: Digit, rhs: Digit, carry: bool) -> (Digit, bool) {
+    let (digit_sum, digit_carry) = lhs.add_carry(rhs);
+    let (full_sum, full_carry) = digit_sum.add_carry(Digit::from_bool(carry));
+    (full_sum, digit_carry || full_carry)
+}
+
+fn sub_digits<Digit: Value>(lhs: Digit, rhs: Digit, borrow: bool) -> (Digit, bool) {
+    let (digit_difference, digit_borrow) = lhs.sub_borrow(rhs);
+    let (full_difference, full_borrow) = digit_difference.sub_borrow(Digit::from_bool(borrow));
+    (full_difference, digit_borrow || full_borrow)
+}
+
+/// The carry only takes two values, so the digits of a sum always start repeating and this
+/// works them all out, however long the period gets.
+impl<Digit: Value> Add for &EventuallyPeriodicPadic<Digit> {
+    type Output = EventuallyPeriodicPadic<Digit>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.checked_add(rhs, usize::MAX).unwrap()
+    }
+}
+
+impl<Digit: Value> Add for EventuallyPeriodicPadic<Digit> {
+    type Output = EventuallyPeriodicPadic<Digit>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        &self + &rhs
+    }
+}
+
+impl<Digit: Value> Sub for &EventuallyPeriodicPadic<Digit> {
+    type Output = EventuallyPeriodicPadic<Digit>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.checked_sub(rhs, usize::MAX).unwrap()
+    }
+}
+
+impl<Digit: Value> Sub for EventuallyPeriodicPadic<Digit> {
+    type Output = EventuallyPeriodicPadic<Digit>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        &self - &rhs
+    }
+}
+
+/// Only the digits from the scale up are negated, and the lowest of them stays nonzero, so the
+/// result keeps the scale and the work doesn't depend on it.
+impl<Digit: Value> Neg for &EventuallyPeriodicPadic<Digit> {
+    type Output = EventuallyPeriodicPadic<Digit>;
+
+    fn neg(self) -> Self::Output {
+        let unit = EventuallyPeriodicPadic { scale: 0, ..self.clone() };
+        EventuallyPeriodicPadic::zero().combine(&unit, sub_digits, usize::MAX).unwrap().shift(self.scale)
+    }
+}
+
+impl<Digit: Value> Neg for EventuallyPeriodicPadic<Digit> {
+    type Output = EventuallyPeriodicPadic<Digit>;
+
+    fn neg(self) -> Self::Output {
+        -&self
+    }
+}
+
+impl<Digit: Value> Mul for &EventuallyPeriodicPadic<Digit> {
+    type Output = EventuallyPeriodicPadic<Digit>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.checked_mul(rhs, usize::MAX).unwrap()
+    }
+}
+
+impl<Digit: Value> Mul for EventuallyPeriodicPadic<Digit> {
+    type Output = EventuallyPeriodicPadic<Digit>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        &self * &rhs
+    }
+}
+
+/// The remainder of the long division is less than the denominator, so the digits always start
+/// repeating, but the period can be as long as the order of p modulo the denominator. Use
+/// [`EventuallyPeriodicPadic::checked_div`] to put a bound on that work.
+impl<Digit: Value> Div for &EventuallyPeriodicPadic<Digit> {
+    type Output = EventuallyPeriodicPadic<Digit>;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        assert!(!rhs.is_zero(), "attempt to divide by zero");
+        self.checked_div(rhs, usize::MAX).unwrap()
+    }
+}
+
+impl<Digit: Value> Div for EventuallyPeriodicPadic<Digit> {
+    type Output = EventuallyPeriodicPadic<Digit>;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        &self / &rhs
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for EventuallyPeriodicPadic<Digit> {
+    fn get_digit(&self, index: isize) -> Digit {
+        if index < self.scale {
+            Digit::zero()
+        } else {
+            self.unit_digit(index.abs_diff(self.scale))
+        }
+    }
+
+    fn get_scale(&self) -> isize {
+        self.scale
+    }
+
+    fn get_valuation(&self, limit: isize) -> Option<isize> {
+        (!self.is_zero() && self.scale < limit).then_some(self.scale)
+    }
+
+    fn to_dyn(self) -> PadicNumber<'a, Digit> {
+        PadicNumber::from_exact(self)
+    }
+}
+
+/// Writes the number the way `parse_padic` reads it, e.g. `_01.1` for 1/3 - 1/2 in base 2.
+impl<Digit: Value> Display for EventuallyPeriodicPadic<Digit> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut digits = vec![Digit::zero(); self.scale.max(0) as usize];
+        digits.extend_from_slice(&self.pre_period);
+        let mut period = self.period.clone();
+        let fraction_len = (-self.scale).max(0) as usize;
+        while digits.len() < fraction_len {
+            digits.push(period[0]);
+            period.rotate_left(1);
+        }
+        if period.len() > 1 || !period[0].is_zero() {
+            write!(f, "{}", "_".repeat(period.len()))?;
+            for digit in period.iter().rev() {
+                write!(f, "{}", digit)?;
+            }
+        } else if digits.len() == fraction_len {
+            write!(f, "0")?;
+        }
+        for (index, digit) in digits.iter().enumerate().rev() {
+            if index + 1 == fraction_len {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", digit)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discrete::AddGroupU8;
+
+    type Exact = EventuallyPeriodicPadic<AddGroupU8<5>>;
+
+    fn fraction(numerator: i64, denominator: i64) -> Exact {
+        &Exact::from_integer(numerator) / &Exact::from_integer(denominator)
+    }
+
+    fn len<Digit: Value>(number: &EventuallyPeriodicPadic<Digit>) -> usize {
+        number.pre_period().len() + number.period().len()
+    }
+
+    #[test]
+    fn arithmetic_on_fractions() {
+        let fractions = [fraction(1, 3), fraction(-2, 7), fraction(5, 4), fraction(3, 25), fraction(-1, 1)];
+        for a in &fractions {
+            assert_eq!(&(a - a), &Exact::zero());
+            assert_eq!(&(a + &-a), &Exact::zero());
+            assert_eq!(&(&(a + &fraction(1, 2)) - &fraction(1, 2)), a);
+            assert_eq!(&(&(a * &fraction(3, 1)) / &fraction(3, 1)), a);
+            assert_eq!(&(a / a), &Exact::one());
+        }
+        assert_eq!(&(&fraction(1, 3) + &fraction(2, 3)), &Exact::one());
+        assert_eq!(&(&fraction(2, 3) * &fraction(3, 2)), &Exact::one());
+        assert_eq!(fraction(3, 25).scale(), -2);
+    }
+
+    #[test]
+    fn checked_results_stay_within_the_limit() {
+        let fractions = [fraction(1, 3), fraction(-2, 7), fraction(5, 4), fraction(1, 11), fraction(3, 25)];
+        for a in &fractions {
+            for b in &fractions {
+                let mut fitted = false;
+                for limit in 0..150 {
+                    let results = [
+                        a.checked_add(b, limit),
+                        a.checked_sub(b, limit),
+                        a.checked_mul(b, limit),
+                        a.checked_div(b, limit),
+                    ];
+                    for result in results.iter().flatten() {
+                        assert!(len(result) <= limit);
+                    }
+                    // a bigger limit never refuses what a smaller one allowed
+                    if fitted {
+                        assert!(results.iter().all(Option::is_some));
+                    }
+                    fitted = results.iter().all(Option::is_some);
+                }
+                assert!(fitted);
+            }
+        }
+    }
+
+    #[test]
+    fn operators_work_out_long_periods() {
+        type Binary = EventuallyPeriodicPadic<AddGroupU8<2>>;
+        let one = Binary::one();
+        for denominator in [2027, 2029, 4093] {
+            let denominator = Binary::from_integer(denominator);
+            let reciprocal = &one / &denominator;
+            assert!(len(&reciprocal) > EXACT_DIGIT_LIMIT);
+            assert_eq!(&(&reciprocal * &denominator), &one);
+            assert_eq!(&(&(&reciprocal + &one) - &one), &reciprocal);
+        }
+    }
+
+    #[test]
+    fn negation_and_shifts_keep_far_scales() {
+        let third = fraction(1, 3);
+        for scale in [10_000_000, 1 << 34, isize::MAX, -(1 << 34), isize::MIN] {
+            let far = third.shift(scale);
+            let negated = -&far;
+            assert_eq!(negated.scale(), scale);
+            assert_eq!(&negated, &(-&third).shift(scale));
+            assert_eq!(&-&negated, &far);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "a digit index overflows")]
+    fn shifting_below_isize_min_panics() {
+        fraction(1, 3).shift(isize::MIN).shift(-1);
+    }
+
+    #[test]
+    #[should_panic(expected = "a digit index overflows")]
+    fn products_past_isize_max_panic() {
+        let third = fraction(1, 3);
+        let _ = third.shift(isize::MAX).checked_mul(&third.shift(1), EXACT_DIGIT_LIMIT);
+    }
+
+    #[test]
+    #[should_panic(expected = "a digit index overflows")]
+    fn quotients_past_isize_max_panic() {
+        let third = fraction(1, 3);
+        let _ = third.shift(isize::MAX).checked_div(&third.shift(-1), EXACT_DIGIT_LIMIT);
+    }
+
+    #[test]
+    fn scale_gaps_past_the_limit_are_refused() {
+        let far = Exact::one().shift(isize::MAX);
+        assert_eq!(Exact::one().checked_add(&far, EXACT_DIGIT_LIMIT), None);
+        assert_eq!(far.checked_sub(&Exact::one().shift(isize::MIN), EXACT_DIGIT_LIMIT), None);
+        let near = Exact::one().shift(EXACT_DIGIT_LIMIT as isize / 2);
+        assert!(Exact::one().checked_add(&near, EXACT_DIGIT_LIMIT).is_some());
+    }
+
+    #[test]
+    fn checked_division_by_zero_is_none() {
+        assert_eq!(Exact::one().checked_div(&Exact::zero(), usize::MAX), None);
+    }
+}
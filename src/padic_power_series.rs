@@ -0,0 +1,423 @@
+use crate::discrete::Value;
+use crate::padic::{raise, CellGuard, PadicError, PadicNumber};
+use crate::padic_periodic::EventuallyPeriodicPadic;
+use std::cell::Cell;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::rc::Rc;
+
+/// A formal power series `a_0 + a_1 x + a_2 x^2 + ...` with lazily generated coefficients.
+///
+/// Coefficients are only generated when asked for, and are then cached, so series built out of
+/// other series (sums, products, inverses, compositions) only ever compute what's needed. Cloning
+/// shares the coefficients.
+pub struct PowerSeries<'a, Digit: Value> {
+    coefficients: Rc<PowerSeriesCoefficients<'a, Digit>>,
+}
+
+/// Makes coefficient `n` out of the coefficients before it.
+type CoefficientGenerator<'a, Digit> = dyn Fn(usize, &[PadicNumber<'a, Digit>]) -> PadicNumber<'a, Digit> + 'a;
+
+/// The cache is taken while the generator runs, so a generator that asks its own series for a
+/// coefficient panics with [`PadicError::IllFounded`] instead of starting over.
+struct PowerSeriesCoefficients<'a, Digit: Value> {
+    generator: Box<CoefficientGenerator<'a, Digit>>,
+    cache: Cell<Option<Vec<PadicNumber<'a, Digit>>>>,
+}
+
+impl<'a, Digit: Value + 'a> PowerSeries<'a, Digit> {
+    pub fn from_fn(coefficient: impl Fn(usize) -> PadicNumber<'a, Digit> + 'a) -> Self {
+        Self::recursive(move |n, _| coefficient(n))
+    }
+
+    /// A polynomial, so every coefficient after `coefficients` is zero.
+    pub fn from_coefficients(coefficients: Vec<PadicNumber<'a, Digit>>) -> Self {
+        Self::from_fn(move |n| coefficients.get(n).cloned().unwrap_or_else(zero))
+    }
+
+    /// The series `x`.
+    pub fn variable() -> Self {
+        Self::from_coefficients(vec![zero(), one()])
+    }
+
+    fn recursive(
+        generator: impl Fn(usize, &[PadicNumber<'a, Digit>]) -> PadicNumber<'a, Digit> + 'a,
+    ) -> Self {
+        PowerSeries {
+            coefficients: Rc::new(PowerSeriesCoefficients {
+                generator: Box::new(generator),
+                cache: Cell::new(Some(vec![])),
+            }),
+        }
+    }
+
+    pub fn coefficient(&self, n: usize) -> PadicNumber<'a, Digit> {
+        let mut cache = CellGuard::take(&self.coefficients.cache);
+        let Some(coefficients) = cache.value.as_mut() else {
+            raise(PadicError::IllFounded);
+        };
+        while coefficients.len() <= n {
+            let coefficient = (self.coefficients.generator)(coefficients.len(), coefficients);
+            coefficients.push(coefficient);
+        }
+        coefficients[n].clone()
+    }
+
+    /// The multiplicative inverse, which exists whenever the constant coefficient isn't zero.
+    ///
+    /// Fails with [`PadicError::DivisionByZero`] when the constant coefficient is an exact zero.
+    /// A lazy one isn't read here: its valuation is only looked for once a coefficient of the
+    /// inverse needs it, and like for [`PadicNumber::reciprocal`], that never finishes if it's
+    /// zero, since no number of zero digits tells a lazy zero apart from a small number.
+    pub fn inverse(&self) -> Result<Self, PadicError> {
+        let constant = self.coefficient(0);
+        if constant.exact().is_some_and(EventuallyPeriodicPadic::is_zero) {
+            return Err(PadicError::DivisionByZero);
+        }
+        let series = self.clone();
+        let constant_inverse = constant.reciprocal();
+        Ok(Self::recursive(move |n, previous| {
+            if n == 0 {
+                return constant_inverse.clone();
+            }
+            // a_0 b_n + a_1 b_(n-1) + ... + a_n b_0 = 0
+            let rest: PadicNumber<'a, Digit> =
+                (1..=n).map(|k| series.coefficient(k) * previous[n - k].clone()).sum();
+            zero() - constant_inverse.clone() * rest
+        }))
+    }
+
+    /// `self(inner(x))`, which needs the constant coefficient of `inner` to be zero.
+    ///
+    /// That's checked right away when the constant coefficient is exact. Otherwise reading a digit
+    /// of a coefficient panics with [`PadicError::NotConvergent`] (see
+    /// [`PadicNumber::try_fill_digits`]) once the constant coefficient has a nonzero digit there.
+    pub fn compose(&self, inner: &Self) -> Result<Self, PadicError> {
+        let constant = inner.coefficient(0);
+        if constant.try_eq(&zero()) == Some(false) {
+            return Err(PadicError::NotConvergent);
+        }
+        let outer = self.clone();
+        let inner = inner.clone();
+        // inner^k, which only has coefficients from x^k up
+        let powers: Cell<Vec<Self>> = Cell::new(vec![]);
+        Ok(Self::from_fn(move |n| {
+            let coefficient: PadicNumber<'a, Digit> = {
+                let mut inner_powers = CellGuard::take(&powers);
+                let inner_powers = &mut inner_powers.value;
+                while inner_powers.len() <= n {
+                    let next = match inner_powers.last() {
+                        Some(power) => power * &inner,
+                        None => PowerSeries::from_coefficients(vec![one()]),
+                    };
+                    inner_powers.push(next);
+                }
+                (0..=n).map(|k| outer.coefficient(k) * inner_powers[k].coefficient(n)).sum()
+            };
+            if constant.exact().is_some() {
+                return coefficient;
+            }
+            let constant = constant.clone();
+            let scale = coefficient.get_scale().min(constant.get_scale());
+            PadicNumber::from_fn(scale, move |index| {
+                if !constant.get_digit(index).is_zero() {
+                    raise(PadicError::NotConvergent);
+                }
+                coefficient.get_digit(index)
+            })
+        }))
+    }
+
+    pub fn derivative(&self) -> Self {
+        let series = self.clone();
+        Self::from_fn(move |n| {
+            PadicNumber::from_exact(EventuallyPeriodicPadic::from_natural(n as u64 + 1)) * series.coefficient(n + 1)
+        })
+    }
+
+    /// Sums the series at `point`, which converges when `point` has positive valuation, as long
+    /// as the coefficients are p-adic integers.
+    ///
+    /// Same as [`Self::eval_with_coefficient_bound`] with a bound of 0 for every coefficient.
+    pub fn eval(&self, point: &PadicNumber<'a, Digit>) -> Result<PadicNumber<'a, Digit>, PadicError> {
+        self.eval_with_coefficient_bound(point, |_| 0)
+    }
+
+    /// Sums the series at `point`, given that coefficient `n` has a valuation of at least
+    /// `coefficient_bound(n)`, like `-floor(log_p n)` for the coefficients `1 / n` of a logarithm.
+    ///
+    /// With `v` the valuation of `point`, `n v + coefficient_bound(n)` bounds the valuation of term
+    /// `n`, and has to be nondecreasing and tend to infinity. Fails if the valuation of `point` can
+    /// be seen to be zero or less, and reading a digit panics with
+    /// [`PadicError::CoefficientBelowBound`] (see [`PadicNumber::try_fill_digits`]) once a
+    /// coefficient it needs turns out to have a nonzero digit below its bound.
+    pub fn eval_with_coefficient_bound(
+        &self,
+        point: &PadicNumber<'a, Digit>,
+        coefficient_bound: impl Fn(usize) -> isize + 'a,
+    ) -> Result<PadicNumber<'a, Digit>, PadicError> {
+        if point.valuation(1).is_some() {
+            return Err(PadicError::NotConvergent);
+        }
+        let valuation = point.get_scale().max(1);
+        let series = self.clone();
+        let point = point.clone();
+        let powers: Cell<Vec<PadicNumber<'a, Digit>>> = Cell::new(vec![]);
+        let coefficient_bound = Rc::new(coefficient_bound);
+        let term_bound = coefficient_bound.clone();
+        Ok(PadicNumber::sum_series(
+            move |n| {
+                let coefficient = series.coefficient(n);
+                if coefficient.valuation(coefficient_bound(n)).is_some() {
+                    raise(PadicError::CoefficientBelowBound { index: n });
+                }
+                let mut point_powers = CellGuard::take(&powers);
+                let point_powers = &mut point_powers.value;
+                while point_powers.len() <= n {
+                    let next = match point_powers.last() {
+                        Some(power) => power.clone() * point.clone(),
+                        None => one(),
+                    };
+                    point_powers.push(next);
+                }
+                coefficient * point_powers[n].clone()
+            },
+            move |n| n as isize * valuation + term_bound(n),
+        ))
+    }
+}
+
+fn zero<'a, Digit: Value + 'a>() -> PadicNumber<'a, Digit> {
+    PadicNumber::from_exact(EventuallyPeriodicPadic::zero())
+}
+
+fn one<'a, Digit: Value + 'a>() -> PadicNumber<'a, Digit> {
+    PadicNumber::from_exact(EventuallyPeriodicPadic::one())
+}
+
+impl<'a, Digit: Value> Clone for PowerSeries<'a, Digit> {
+    fn clone(&self) -> Self {
+        PowerSeries {
+            coefficients: self.coefficients.clone(),
+        }
+    }
+}
+
+impl<'a, Digit: Value + 'a> Add for &PowerSeries<'a, Digit> {
+    type Output = PowerSeries<'a, Digit>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let (lhs, rhs) = (self.clone(), rhs.clone());
+        PowerSeries::from_fn(move |n| lhs.coefficient(n) + rhs.coefficient(n))
+    }
+}
+
+impl<'a, Digit: Value + 'a> Add for PowerSeries<'a, Digit> {
+    type Output = PowerSeries<'a, Digit>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        &self + &rhs
+    }
+}
+
+impl<'a, Digit: Value + 'a> Sub for &PowerSeries<'a, Digit> {
+    type Output = PowerSeries<'a, Digit>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        let (lhs, rhs) = (self.clone(), rhs.clone());
+        PowerSeries::from_fn(move |n| lhs.coefficient(n) - rhs.coefficient(n))
+    }
+}
+
+impl<'a, Digit: Value + 'a> Sub for PowerSeries<'a, Digit> {
+    type Output = PowerSeries<'a, Digit>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        &self - &rhs
+    }
+}
+
+impl<'a, Digit: Value + 'a> Neg for &PowerSeries<'a, Digit> {
+    type Output = PowerSeries<'a, Digit>;
+
+    fn neg(self) -> Self::Output {
+        let series = self.clone();
+        PowerSeries::from_fn(move |n| zero() - series.coefficient(n))
+    }
+}
+
+impl<'a, Digit: Value + 'a> Neg for PowerSeries<'a, Digit> {
+    type Output = PowerSeries<'a, Digit>;
+
+    fn neg(self) -> Self::Output {
+        -&self
+    }
+}
+
+/// Coefficient `n` of the product is one n-ary sum of `n + 1` products.
+impl<'a, Digit: Value + 'a> Mul for &PowerSeries<'a, Digit> {
+    type Output = PowerSeries<'a, Digit>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let (lhs, rhs) = (self.clone(), rhs.clone());
+        PowerSeries::from_fn(move |n| (0..=n).map(|k| lhs.coefficient(k) * rhs.coefficient(n - k)).sum())
+    }
+}
+
+impl<'a, Digit: Value + 'a> Mul for PowerSeries<'a, Digit> {
+    type Output = PowerSeries<'a, Digit>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        &self * &rhs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discrete::AddGroupU8;
+    use crate::padic_shift::ShiftedPadicNumber;
+    use crate::padic_test_util::{fraction, try_digits};
+
+    type Series<'a> = PowerSeries<'a, AddGroupU8<3>>;
+
+    /// `1 / (1 - x)`
+    fn geometric() -> Series<'static> {
+        Series::from_coefficients(vec![fraction(1, 1), fraction(-1, 1)]).inverse().unwrap()
+    }
+
+    /// `log(1 + x)`, whose coefficients `(-1)^(n+1) / n` have valuation at least `-floor(log_3 n)`.
+    fn log_one_plus_x() -> Series<'static> {
+        Series::from_fn(|n| match n {
+            0 => fraction(0, 1),
+            _ => fraction(if n % 2 == 1 { 1 } else { -1 }, n as i64),
+        })
+    }
+
+    fn log_bound(n: usize) -> isize {
+        -(n.max(1).ilog(3) as isize)
+    }
+
+    #[test]
+    fn inverse_of_one_minus_x() {
+        let series = Series::from_coefficients(vec![fraction(1, 1), zero::<AddGroupU8<3>>() - fraction(1, 1)]);
+        let inverse = series.inverse().unwrap();
+        for n in 0..5 {
+            assert_eq!(try_digits(&inverse.coefficient(n), -2..4), Ok(vec![0, 0, 1, 0, 0, 0]));
+        }
+    }
+
+    #[test]
+    fn inverse_of_an_exact_zero_constant_fails() {
+        let series = Series::variable();
+        assert_eq!(series.inverse().err(), Some(PadicError::DivisionByZero));
+    }
+
+    #[test]
+    fn inverse_with_a_lazy_constant() {
+        let constant = PadicNumber::new(ShiftedPadicNumber::new(fraction(1, 2), 0));
+        let series = Series::from_coefficients(vec![constant, fraction(-1, 2)]);
+        let inverse = series.inverse().unwrap();
+        for n in 0..5 {
+            assert_eq!(try_digits(&inverse.coefficient(n), 0..8), try_digits(&fraction::<3>(2, 1), 0..8));
+        }
+    }
+
+    #[test]
+    fn product_with_the_inverse_is_one() {
+        let series = Series::from_coefficients(vec![fraction(1, 1), fraction(-1, 1)]);
+        let product = &series * &geometric();
+        assert_eq!(try_digits(&product.coefficient(0), -2..8), try_digits(&fraction::<3>(1, 1), -2..8));
+        for n in 1..6 {
+            assert_eq!(try_digits(&product.coefficient(n), -2..8), Ok(vec![0; 10]));
+        }
+    }
+
+    #[test]
+    fn derivative_of_a_geometric_series() {
+        let derivative = geometric().derivative();
+        for n in 0..6 {
+            assert_eq!(try_digits(&derivative.coefficient(n), 0..8), try_digits(&fraction::<3>(n as i64 + 1, 1), 0..8));
+        }
+        // 1 / (1 - x)^2 at x = 3
+        let value = derivative.eval(&fraction(3, 1)).unwrap();
+        assert_eq!(try_digits(&value, 0..20), try_digits(&fraction::<3>(1, 4), 0..20));
+    }
+
+    #[test]
+    fn eval_at_a_multiple_of_the_base() {
+        let value = geometric().eval(&fraction(3, 1)).unwrap();
+        assert_eq!(try_digits(&value, 0..20), try_digits(&fraction::<3>(-1, 2), 0..20));
+        let polynomial = Series::from_coefficients(vec![fraction(1, 1), fraction(1, 1), fraction(1, 1)]);
+        let value = polynomial.eval(&fraction(3, 2)).unwrap();
+        assert_eq!(try_digits(&value, -2..20), try_digits(&fraction::<3>(19, 4), -2..20));
+    }
+
+    #[test]
+    fn eval_needs_a_positive_valuation() {
+        assert_eq!(geometric().eval(&fraction(1, 1)).err(), Some(PadicError::NotConvergent));
+        assert_eq!(geometric().eval(&fraction(1, 3)).err(), Some(PadicError::NotConvergent));
+    }
+
+    #[test]
+    fn logarithm_turns_products_into_sums() {
+        // (1 + 3)^2 = 1 + 15
+        let log_4 = log_one_plus_x().eval_with_coefficient_bound(&fraction(3, 1), log_bound).unwrap();
+        let log_16 = log_one_plus_x().eval_with_coefficient_bound(&fraction(15, 1), log_bound).unwrap();
+        assert_eq!(try_digits(&log_16, 0..15), try_digits(&(log_4.clone() + log_4.clone()), 0..15));
+        // log(1 + 3) = 3 - 9/2 + 9 - ..., and the rest is a multiple of 27
+        assert_eq!(try_digits(&log_4, 0..3), try_digits(&fraction::<3>(15, 2), 0..3));
+    }
+
+    #[test]
+    fn logarithm_breaks_a_bound_of_zero() {
+        let log_4 = log_one_plus_x().eval(&fraction(3, 1)).unwrap();
+        assert_eq!(try_digits(&log_4, 0..10), Err(PadicError::CoefficientBelowBound { index: 3 }));
+    }
+
+    #[test]
+    fn composing_with_a_square() {
+        let inner = Series::from_coefficients(vec![fraction(0, 1), fraction(0, 1), fraction(1, 1)]);
+        let composed = geometric().compose(&inner).unwrap();
+        for n in 0..6 {
+            assert_eq!(try_digits(&composed.coefficient(n), 0..4), try_digits(&fraction::<3>(((n + 1) % 2) as i64, 1), 0..4));
+        }
+        // 1 / (1 - 9)
+        let value = composed.eval(&fraction(3, 1)).unwrap();
+        assert_eq!(try_digits(&value, 0..20), try_digits(&fraction::<3>(-1, 8), 0..20));
+    }
+
+    #[test]
+    fn composing_with_a_lazy_zero_constant() {
+        let constant = PadicNumber::new(ShiftedPadicNumber::new(fraction(0, 1), 0));
+        let inner = Series::from_coefficients(vec![constant, fraction(3, 1)]);
+        let composed = geometric().compose(&inner).unwrap();
+        for n in 0..4 {
+            assert_eq!(try_digits(&composed.coefficient(n), 0..8), try_digits(&fraction::<3>(3i64.pow(n as u32), 1), 0..8));
+        }
+    }
+
+    #[test]
+    fn composing_with_a_lazy_nonzero_constant_fails_when_read() {
+        let constant = PadicNumber::new(ShiftedPadicNumber::new(fraction(3, 1), 0));
+        let inner = Series::from_coefficients(vec![constant, fraction(1, 1)]);
+        let composed = Series::variable().compose(&inner).unwrap();
+        let coefficient = composed.coefficient(0);
+        assert_eq!(try_digits(&coefficient, 0..1), Ok(vec![0]));
+        assert_eq!(try_digits(&coefficient, 0..2), Err(PadicError::NotConvergent));
+    }
+
+    #[test]
+    #[should_panic(expected = "depends on itself")]
+    fn generator_reading_its_own_series_is_ill_founded() {
+        let this: Rc<Cell<Option<Series<'static>>>> = Rc::new(Cell::new(None));
+        let reference = this.clone();
+        let series = Series::from_fn(move |n| {
+            let series = reference.take().unwrap();
+            let coefficient = if n == 0 { fraction(1, 1) } else { series.coefficient(n - 1) };
+            reference.set(Some(series));
+            coefficient
+        });
+        this.set(Some(series.clone()));
+        series.coefficient(1);
+    }
+}
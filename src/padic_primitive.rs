@@ -1,5 +1,7 @@
 use crate::discrete::Value;
-use crate::padic::PadicAccessor;
+use crate::padic::{raise, CellGuard, PadicAccessor, PadicError, PadicNumber};
+use std::cell::Cell;
+use std::ops::Range;
 
 pub struct FinitePadicInteger<Digit: Value> {
     digits: Vec<Digit>,
@@ -58,3 +60,106 @@ impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for RepeatingPadicInteger<D
         0
     }
 }
+
+impl<'a, Digit: Value + 'a> PadicNumber<'a, Digit> {
+    /// A number whose digit `i` is `digit(i)`, for every `i` from `scale` up (the digits below
+    /// are zero). See [`FunctionPadicNumber`].
+    pub fn from_fn(scale: isize, digit: impl Fn(isize) -> Digit + 'a) -> PadicNumber<'a, Digit> {
+        PadicNumber::new(FunctionPadicNumber::new(scale, digit))
+    }
+}
+
+/// Digits given by a closure, memoized.
+///
+/// The closure is called at most once per index, in increasing order from `scale` up: asking for a
+/// digit first computes every digit below it that hasn't been computed yet. The cache is taken
+/// while the closure runs, so a digit read from inside it, even of a digit that is already known,
+/// panics with [`PadicError::IllFounded`].
+pub struct FunctionPadicNumber<'a, Digit: Value> {
+    digit: Box<dyn Fn(isize) -> Digit + 'a>,
+    scale: isize,
+    cache: Cell<Option<Vec<Digit>>>,
+}
+
+impl<'a, Digit: Value> FunctionPadicNumber<'a, Digit> {
+    pub fn new(scale: isize, digit: impl Fn(isize) -> Digit + 'a) -> Self {
+        FunctionPadicNumber {
+            digit: Box::new(digit),
+            scale,
+            cache: Cell::new(Some(vec![])),
+        }
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for FunctionPadicNumber<'a, Digit> {
+    fn get_digit(&self, index: isize) -> Digit {
+        let mut digit = Digit::zero();
+        self.fill_digits(index..index + 1, std::slice::from_mut(&mut digit));
+        digit
+    }
+
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        let mut cache = CellGuard::take(&self.cache);
+        let Some(cache) = cache.value.as_mut() else {
+            raise(PadicError::IllFounded);
+        };
+
+        let end = (range.end - self.scale).max(0) as usize;
+        for i in cache.len()..end {
+            cache.push((self.digit)(self.scale + i as isize));
+        }
+        for (digit, index) in digits.iter_mut().zip(range) {
+            *digit = if index < self.scale {
+                Digit::zero()
+            } else {
+                cache[(index - self.scale) as usize]
+            };
+        }
+    }
+
+    fn get_scale(&self) -> isize {
+        self.scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discrete::AddGroupU8;
+    use std::cell::RefCell;
+    use std::rc::{Rc, Weak};
+
+    type Digit = AddGroupU8<2>;
+
+    #[test]
+    fn digits_are_kept_after_a_failed_read() {
+        let calls = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+        let number = PadicNumber::from_fn(0, move |index| {
+            counter.set(counter.get() + 1);
+            if index == 2 && counter.get() == 3 {
+                raise(PadicError::OutOfRange);
+            }
+            Digit::new(1).unwrap()
+        });
+        let mut digits = [Digit::new(0).unwrap(); 4];
+        assert_eq!(number.try_fill_digits(0..4, &mut digits), Err(PadicError::OutOfRange));
+        assert!(number.try_fill_digits(0..4, &mut digits).is_ok());
+        // only digit 2, which failed the first time, was computed twice
+        assert_eq!(calls.get(), 5);
+    }
+
+    #[test]
+    fn reading_itself_is_ill_founded() {
+        let this: Rc<RefCell<Weak<dyn PadicAccessor<'static, Digit>>>> =
+            Rc::new(RefCell::new(Weak::<FinitePadicInteger<Digit>>::new()));
+        let reference = this.clone();
+        let number = PadicNumber::from_fn(0, move |index| {
+            let this = reference.borrow().upgrade().unwrap();
+            if index > 0 { this.get_digit(index - 1) } else { Digit::new(1).unwrap() }
+        });
+        *this.borrow_mut() = Rc::downgrade(&number.value);
+        let mut digits = [Digit::new(0).unwrap(); 2];
+        assert_eq!(number.try_fill_digits(0..2, &mut digits), Err(PadicError::IllFounded));
+    }
+}
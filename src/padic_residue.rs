@@ -0,0 +1,476 @@
+use crate::digit_vec;
+use crate::discrete::{AddGroupU8, Value};
+use crate::padic::{raise, CellGuard, PadicAccessor, PadicError, PadicNumber};
+use crate::padic_periodic::EventuallyPeriodicPadic;
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::ops::{Add, Mul, Neg, Range, Sub};
+
+/// An integer mod p^n, stored as its n lowest digits.
+///
+/// Combining two of these with different `n` reduces the more precise one first, so the result
+/// has the smaller `n`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModPn<Digit: Value> {
+    /// The representative from 0 to p^n - 1, trimmed like every `digit_vec`.
+    value: Vec<Digit>,
+    precision: usize,
+}
+
+impl<Digit: Value> ModPn<Digit> {
+    /// The digits are little-endian, and the ones from `precision` up are dropped.
+    pub fn new(digits: Vec<Digit>, precision: usize) -> Self {
+        let mut value = digits;
+        value.truncate(precision);
+        ModPn { value: digit_vec::trim(value), precision }
+    }
+
+    pub fn zero(precision: usize) -> Self {
+        Self::new(vec![], precision)
+    }
+
+    pub fn one(precision: usize) -> Self {
+        Self::new(vec![Digit::one()], precision)
+    }
+
+    /// The `n` in p^n.
+    pub fn precision(&self) -> usize {
+        self.precision
+    }
+
+    /// Digit `i` of the representative from 0 to p^n - 1.
+    pub fn digit(&self, index: usize) -> Digit {
+        self.value.get(index).copied().unwrap_or(Digit::zero())
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    /// Units are exactly the numbers that aren't multiples of p.
+    pub fn is_unit(&self) -> bool {
+        self.precision > 0 && !self.digit(0).is_zero()
+    }
+
+    /// The image in F_p, which is the lowest digit.
+    pub fn residue(&self) -> Digit {
+        self.digit(0)
+    }
+
+    /// Reduces to a lower precision.
+    pub fn reduce(&self, precision: usize) -> Self {
+        Self::new(self.value.clone(), precision.min(self.precision))
+    }
+
+    /// The canonical lift: the p-adic integer from 0 to p^n - 1 with the same digits.
+    pub fn lift<'a>(&self) -> PadicNumber<'a, Digit>
+    where
+        Digit: 'a,
+    {
+        PadicNumber::from_exact(EventuallyPeriodicPadic::new(0, self.value.clone(), vec![]))
+    }
+
+    /// The multiplicative inverse, for units, found with Newton's iteration
+    /// `x -> x (2 - self x)`, which doubles the correct digits every time.
+    pub fn inverse(&self) -> Option<Self> {
+        if !self.is_unit() {
+            return None;
+        }
+        let mut inverse = Self::new(vec![self.digit(0).inverse()], 1);
+        while inverse.precision < self.precision {
+            let precision = (2 * inverse.precision).min(self.precision);
+            let inverse_lift = Self::new(inverse.value, precision);
+            let two = Self::one(precision) + Self::one(precision);
+            inverse = &inverse_lift * &(two - &self.reduce(precision) * &inverse_lift);
+        }
+        Some(inverse)
+    }
+
+    /// p^n itself, as a digit vector.
+    fn modulus(&self) -> Vec<Digit> {
+        let mut modulus = vec![Digit::zero(); self.precision];
+        modulus.push(Digit::one());
+        modulus
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicNumber<'a, Digit> {
+    /// `self` mod p^n. Fails if a digit after the point isn't zero.
+    pub fn reduce(&self, n: usize) -> Result<ModPn<Digit>, PadicError> {
+        if (self.get_scale()..0).any(|i| !self.get_digit(i).is_zero()) {
+            return Err(PadicError::NotAnInteger);
+        }
+        let mut digits = vec![Digit::zero(); n];
+        self.fill_digits(0..n as isize, &mut digits);
+        Ok(ModPn::new(digits, n))
+    }
+
+    /// The image in F_p (`self` mod p), for p-adic integers.
+    pub fn residue(&self) -> Result<Digit, PadicError> {
+        Ok(self.reduce(1)?.residue())
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicNumber<'a, Digit> {
+    /// The p-adic integer that is `residue(n)` mod p^n for every `n`.
+    ///
+    /// `residue(n)` is asked for with `n = 1, 2, ...` as digits are needed, and has to have a
+    /// precision of at least `n`, or reading the digits it was asked for panics with
+    /// [`PadicError::ResidueBelowPrecision`]. See [`Self::from_residue_iter`].
+    pub fn from_residues(residue: impl Fn(usize) -> ModPn<Digit> + 'a) -> Self {
+        Self::from_checked_residues((1..).map(move |n| {
+            let next = residue(n);
+            if next.precision() < n { Err(PadicError::ResidueBelowPrecision { n }) } else { Ok(next) }
+        }))
+    }
+
+    /// The p-adic integer that all of `residues` are reductions of.
+    ///
+    /// Every residue is checked against the most precise one before it, and reading a digit
+    /// panics with [`PadicError::IncompatibleResidues`] (see [`Self::try_fill_digits`]) once two
+    /// of them disagree, or with [`PadicError::OutOfRange`] when the residues run out before the
+    /// digit is known. The iterator may read digits of the number that earlier residues already
+    /// fixed, but reading past them panics with [`PadicError::IllFounded`].
+    pub fn from_residue_iter(residues: impl Iterator<Item = ModPn<Digit>> + 'a) -> Self {
+        Self::from_checked_residues(residues.map(Ok))
+    }
+
+    /// Like [`Self::from_residue_iter`], for residues that can fail to come out.
+    fn from_checked_residues(residues: impl Iterator<Item = Result<ModPn<Digit>, PadicError>> + 'a) -> Self {
+        PadicNumber::new(InverseLimitPadicNumber {
+            residues: Cell::new(Some(Box::new(residues))),
+            known: RefCell::new(ModPn::zero(0)),
+            error: Cell::new(None),
+        })
+    }
+
+    /// The p-adic integer that all of `residues` are reductions of, checking up front that they
+    /// agree with each other.
+    ///
+    /// Digits past the most precise residue are unknown, and reading them panics with
+    /// [`PadicError::OutOfRange`].
+    pub fn try_from_residues(residues: impl IntoIterator<Item = ModPn<Digit>>) -> Result<Self, PadicError> {
+        let mut known = ModPn::zero(0);
+        for next in residues {
+            merge_residue(&mut known, next)?;
+        }
+        Ok(Self::from_residue_iter(std::iter::once(known)))
+    }
+}
+
+/// Checks `next` against `known`, keeping the more precise of the two.
+fn merge_residue<Digit: Value>(known: &mut ModPn<Digit>, next: ModPn<Digit>) -> Result<(), PadicError> {
+    let common = next.precision().min(known.precision());
+    if next.reduce(common) != known.reduce(common) {
+        return Err(PadicError::IncompatibleResidues { precision: next.precision() });
+    }
+    if next.precision() > known.precision() {
+        *known = next;
+    }
+    Ok(())
+}
+
+type ResidueIter<'a, Digit> = Box<dyn Iterator<Item = Result<ModPn<Digit>, PadicError>> + 'a>;
+
+/// Digits of the most precise residue seen so far, pulling in more residues when they run out.
+///
+/// The iterator is taken out of its `Cell` while it runs, so it can read the digits already in
+/// `known`, which isn't borrowed then either.
+pub struct InverseLimitPadicNumber<'a, Digit: Value> {
+    residues: Cell<Option<ResidueIter<'a, Digit>>>,
+    known: RefCell<ModPn<Digit>>,
+    /// Once residues disagree or run out, every digit past `known` fails the same way.
+    error: Cell<Option<PadicError>>,
+}
+
+impl<'a, Digit: Value + 'a> InverseLimitPadicNumber<'a, Digit> {
+    fn extend_to(&self, precision: usize) -> Result<(), PadicError> {
+        while self.known.borrow().precision() < precision {
+            let next = {
+                let mut residues = CellGuard::take(&self.residues);
+                let residues = residues.value.as_mut().ok_or(PadicError::IllFounded)?;
+                residues.next().ok_or(PadicError::OutOfRange)??
+            };
+            merge_residue(&mut self.known.borrow_mut(), next)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for InverseLimitPadicNumber<'a, Digit> {
+    fn get_digit(&self, index: isize) -> Digit {
+        let mut digit = Digit::zero();
+        self.fill_digits(index..index + 1, std::slice::from_mut(&mut digit));
+        digit
+    }
+
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        let precision = range.end.max(0) as usize;
+        let result = match self.error.get() {
+            Some(error) if self.known.borrow().precision() < precision => Err(error),
+            _ => self.extend_to(precision),
+        };
+        match result {
+            Ok(()) => {}
+            // a read from inside the iterator, which doesn't say anything about later reads
+            Err(PadicError::IllFounded) => raise(PadicError::IllFounded),
+            Err(error) => {
+                self.error.set(Some(error));
+                raise(error);
+            }
+        }
+        let known = self.known.borrow();
+        for (digit, index) in digits.iter_mut().zip(range) {
+            *digit = usize::try_from(index).map_or(Digit::zero(), |index| known.digit(index));
+        }
+    }
+
+    fn get_scale(&self) -> isize {
+        0
+    }
+}
+
+impl<'a, const BASE: u8> PadicNumber<'a, AddGroupU8<BASE>> {
+    /// The Teichmüller lift of `residue`: the only root of `x^p = x` with that residue, so 0 or
+    /// a (p - 1)th root of unity.
+    ///
+    /// It's the fixed point of `x -> x^p` near `residue`, which after writing `x = residue + p y`
+    /// is a contraction in `y`.
+    pub fn teichmuller(residue: AddGroupU8<BASE>) -> Self {
+        let digit = residue.value();
+        if digit <= 1 || digit == BASE - 1 {
+            // 0, 1 and -1 are their own powers
+            let value = if digit > 1 { -1 } else { digit as i64 };
+            return PadicNumber::from_exact(EventuallyPeriodicPadic::from_integer(value));
+        }
+        let lift = move || PadicNumber::from_exact(EventuallyPeriodicPadic::new(0, vec![residue], vec![]));
+        let correction = PadicNumber::contraction_fixed_point(move |y| {
+            let x = lift() + y.shift(1);
+            let power: PadicNumber<'a, AddGroupU8<BASE>> = std::iter::repeat_n(x, BASE as usize).product();
+            (power - lift()).shift(-1)
+        });
+        lift() + correction.shift(1)
+    }
+}
+
+impl<Digit: Value> Display for ModPn<Digit> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for i in (0..self.precision).rev() {
+            write!(f, "{}", self.digit(i))?;
+        }
+        Ok(())
+    }
+}
+
+impl<Digit: Value> Add for &ModPn<Digit> {
+    type Output = ModPn<Digit>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        ModPn::new(digit_vec::add(&self.value, &rhs.value), self.precision.min(rhs.precision))
+    }
+}
+
+impl<Digit: Value> Sub for &ModPn<Digit> {
+    type Output = ModPn<Digit>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        let precision = self.precision.min(rhs.precision);
+        let (lhs, rhs) = (self.reduce(precision), rhs.reduce(precision));
+        match digit_vec::cmp(&lhs.value, &rhs.value) {
+            Ordering::Less => -&ModPn::new(digit_vec::sub(&rhs.value, &lhs.value), precision),
+            _ => ModPn::new(digit_vec::sub(&lhs.value, &rhs.value), precision),
+        }
+    }
+}
+
+impl<Digit: Value> Neg for &ModPn<Digit> {
+    type Output = ModPn<Digit>;
+
+    fn neg(self) -> Self::Output {
+        if self.is_zero() {
+            return self.clone();
+        }
+        ModPn::new(digit_vec::sub(&self.modulus(), &self.value), self.precision)
+    }
+}
+
+impl<Digit: Value> Mul for &ModPn<Digit> {
+    type Output = ModPn<Digit>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let precision = self.precision.min(rhs.precision);
+        let lhs = &self.value[..self.value.len().min(precision)];
+        let rhs = &rhs.value[..rhs.value.len().min(precision)];
+        ModPn::new(digit_vec::mul(lhs, rhs), precision)
+    }
+}
+
+macro_rules! impl_owned_operator {
+    ($trait:ident, $method:ident) => {
+        impl<Digit: Value> $trait for ModPn<Digit> {
+            type Output = ModPn<Digit>;
+
+            fn $method(self, rhs: Self) -> Self::Output {
+                (&self).$method(&rhs)
+            }
+        }
+    };
+}
+
+impl_owned_operator!(Add, add);
+impl_owned_operator!(Sub, sub);
+impl_owned_operator!(Mul, mul);
+
+impl<Digit: Value> Neg for ModPn<Digit> {
+    type Output = ModPn<Digit>;
+
+    fn neg(self) -> Self::Output {
+        -&self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::padic_test_util::{fraction, try_digits};
+    use std::rc::{Rc, Weak};
+
+    type Digit = AddGroupU8<7>;
+    type Padic<'a> = PadicNumber<'a, Digit>;
+
+    const PRECISION: usize = 12;
+    const MODULUS: u128 = 7u128.pow(PRECISION as u32);
+
+    fn from_int(mut value: u128) -> ModPn<Digit> {
+        let digits = (0..PRECISION)
+            .map(|_| {
+                let digit = Digit::new((value % 7) as u8).unwrap();
+                value /= 7;
+                digit
+            })
+            .collect();
+        ModPn::new(digits, PRECISION)
+    }
+
+    fn to_int(x: &ModPn<Digit>) -> u128 {
+        (0..x.precision()).rev().fold(0, |acc, i| acc * 7 + x.digit(i).value() as u128)
+    }
+
+    fn samples() -> impl Iterator<Item = u128> {
+        (0..100u128).map(|i| (i * i * 1_000_003 + i * 977 + 5) % MODULUS)
+    }
+
+    #[test]
+    fn arithmetic_matches_integers_mod_p_n() {
+        for (a, b) in samples().zip(samples().skip(1)) {
+            let (x, y) = (from_int(a), from_int(b));
+            assert_eq!(to_int(&(&x + &y)), (a + b) % MODULUS);
+            assert_eq!(to_int(&(&x - &y)), (a + MODULUS - b) % MODULUS);
+            assert_eq!(to_int(&(&x * &y)), a * b % MODULUS);
+            assert_eq!(to_int(&-&x), (MODULUS - a) % MODULUS);
+            assert_eq!(x.lift().reduce(PRECISION), Ok(x));
+        }
+        assert_eq!((from_int(10) + ModPn::new(vec![Digit::new(3).unwrap()], 1)).precision(), 1);
+    }
+
+    #[test]
+    fn inverse_of_units_only() {
+        for a in samples() {
+            match from_int(a).inverse() {
+                Some(inverse) => assert_eq!(to_int(&(&inverse * &from_int(a))), 1),
+                None => assert_eq!(a % 7, 0),
+            }
+        }
+        assert_eq!(ModPn::<Digit>::one(0).inverse(), None);
+    }
+
+    #[test]
+    fn teichmuller_lifts_are_roots_of_x_p_minus_x() {
+        for value in 0..7 {
+            let residue = Digit::new(value).unwrap();
+            let lift = Padic::teichmuller(residue);
+            let power: Padic = std::iter::repeat_n(lift.clone(), 7).product();
+            assert_eq!((power - lift.clone()).reduce(30), Ok(ModPn::zero(30)));
+            assert_eq!(lift.residue(), Ok(residue));
+        }
+    }
+
+    #[test]
+    fn residues_of_a_number_give_it_back() {
+        let target: Padic = fraction(2, 3);
+        let reductions = target.clone();
+        let number = Padic::from_residues(move |n| reductions.reduce(n).unwrap());
+        assert_eq!(try_digits(&number, -2..40), try_digits(&target, -2..40));
+    }
+
+    #[test]
+    fn residues_running_out() {
+        let target: Padic = fraction(2, 3);
+        let reductions = target.clone();
+        let number = Padic::from_residue_iter([3, 7, 7, 20].into_iter().map(move |n| reductions.reduce(n).unwrap()));
+        assert_eq!(try_digits(&number, 0..20), try_digits(&target, 0..20));
+        assert_eq!(try_digits(&number, 0..21), Err(PadicError::OutOfRange));
+        assert!(try_digits(&number, 0..20).is_ok());
+    }
+
+    #[test]
+    fn incompatible_residues_are_an_error_from_then_on() {
+        let target: Padic = fraction(2, 3);
+        let number = Padic::from_residues(move |n| {
+            let residue = target.reduce(n).unwrap();
+            // off by p^5 from precision 7 on
+            if n >= 7 { residue + ModPn::new([0, 0, 0, 0, 0, 1].map(|d| Digit::new(d).unwrap()).to_vec(), n) } else { residue }
+        });
+        assert!(try_digits(&number, 0..6).is_ok());
+        assert_eq!(try_digits(&number, 0..8), Err(PadicError::IncompatibleResidues { precision: 7 }));
+        assert_eq!(try_digits(&number, 0..9), Err(PadicError::IncompatibleResidues { precision: 7 }));
+        // the digits fixed before the disagreement are still there
+        assert!(try_digits(&number, 0..6).is_ok());
+    }
+
+    #[test]
+    fn residues_below_their_precision_are_an_error() {
+        let number = Padic::from_residues(|_| ModPn::one(1));
+        assert_eq!(try_digits(&number, 0..1), Ok(vec![1]));
+        assert_eq!(try_digits(&number, 0..2), Err(PadicError::ResidueBelowPrecision { n: 2 }));
+        assert_eq!(try_digits(&number, 0..3), Err(PadicError::ResidueBelowPrecision { n: 2 }));
+        assert_eq!(try_digits(&number, 0..1), Ok(vec![1]));
+    }
+
+    #[test]
+    fn checking_residues_up_front() {
+        let one = Digit::new(1).unwrap();
+        let two = Digit::new(2).unwrap();
+        let number = Padic::try_from_residues([ModPn::new(vec![one, two], 2), ModPn::new(vec![one], 1)]).unwrap();
+        assert_eq!(try_digits(&number, 0..2), Ok(vec![1, 2]));
+        assert_eq!(try_digits(&number, 0..3), Err(PadicError::OutOfRange));
+        let disagreeing = Padic::try_from_residues([ModPn::new(vec![one], 1), ModPn::new(vec![two, two], 2)]);
+        assert_eq!(disagreeing.err(), Some(PadicError::IncompatibleResidues { precision: 2 }));
+    }
+
+    #[test]
+    fn residues_reading_unknown_digits_are_ill_founded() {
+        let this: Rc<RefCell<Weak<dyn PadicAccessor<'static, Digit>>>> =
+            Rc::new(RefCell::new(Weak::<EventuallyPeriodicPadic<Digit>>::new()));
+        let reference = this.clone();
+        // residue n only reads digits below n - 1, which the residues before it fixed
+        let counting = Padic::from_residues(move |n| {
+            let this = reference.borrow().upgrade().unwrap();
+            let mut residue: Vec<Digit> = (0..n as isize - 1).map(|i| this.get_digit(i)).collect();
+            residue.push(Digit::new((n % 7) as u8).unwrap());
+            ModPn::new(residue, n)
+        });
+        *this.borrow_mut() = Rc::downgrade(&counting.value);
+        assert_eq!(try_digits(&counting, 0..8), Ok(vec![1, 2, 3, 4, 5, 6, 0, 1]));
+
+        let reference = this.clone();
+        let ahead = Padic::from_residues(move |n| {
+            let digit = reference.borrow().upgrade().unwrap().get_digit(n as isize);
+            ModPn::new(vec![digit; n], n)
+        });
+        *this.borrow_mut() = Rc::downgrade(&ahead.value);
+        assert_eq!(try_digits(&ahead, 0..3), Err(PadicError::IllFounded));
+        assert_eq!(try_digits(&ahead, 0..3), Err(PadicError::IllFounded));
+    }
+}
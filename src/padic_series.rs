@@ -0,0 +1,236 @@
+use crate::digit_vec;
+use crate::discrete::Value;
+use crate::padic::{
+    fill_scaled_digits, raise, CellGuard, ComputingGuard, PadicAccessor, PadicError, PadicIntegerAccessor, PadicNumber, PadicNumberScaler,
+};
+use std::cell::{Cell, RefCell};
+use std::ops::Range;
+use std::rc::Rc;
+
+impl<'a, Digit: Value + 'a> PadicNumber<'a, Digit> {
+    /// Sums `term(0) + term(1) + ...`, where the valuation of `term(n)` is at least
+    /// `valuation_bound(n)`.
+    ///
+    /// The bound has to be nondecreasing and tend to infinity, so that digit `k` only needs the
+    /// terms with `valuation_bound(n) <= k`. Terms are only asked for once a digit needs them, and
+    /// reading a digit panics (see [`PadicNumber::try_fill_digits`]) with
+    /// [`PadicError::TermBelowBound`] if a term it needs has a nonzero digit below its bound, and
+    /// with [`PadicError::DecreasingBound`] if its bound is lower than the one before.
+    ///
+    /// `term(n)` can read the digits of the sum below `valuation_bound(n)`. Reading one at or
+    /// above it needs `term(n)` itself, so that panics with [`PadicError::IllFounded`].
+    pub fn sum_series(
+        term: impl Fn(usize) -> PadicNumber<'a, Digit> + 'a,
+        valuation_bound: impl Fn(usize) -> isize + 'a,
+    ) -> PadicNumber<'a, Digit> {
+        PadicNumber::new(SeriesPadicNumber::new(Box::new(term), Box::new(valuation_bound)))
+    }
+}
+
+pub struct SeriesPadicNumber<'a, Digit: Value> {
+    inner: SeriesPadicInteger<'a, Digit>,
+    scale: isize,
+}
+
+impl<'a, Digit: Value + 'a> SeriesPadicNumber<'a, Digit> {
+    fn new(
+        term: Box<dyn Fn(usize) -> PadicNumber<'a, Digit> + 'a>,
+        valuation_bound: Box<dyn Fn(usize) -> isize + 'a>,
+    ) -> Self {
+        // every bound is at least the first one, so no term has digits below it
+        let scale = valuation_bound(0);
+        SeriesPadicNumber {
+            inner: SeriesPadicInteger {
+                term,
+                valuation_bound,
+                scale,
+                terms: RefCell::new(vec![]),
+                last_bound: Cell::new(scale),
+                making_term: Cell::new(false),
+                cache: Cell::new(SeriesCache::default()),
+            },
+            scale,
+        }
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for SeriesPadicNumber<'a, Digit> {
+    fn get_digit(&self, index: isize) -> Digit {
+        let mut digit = Digit::zero();
+        self.fill_digits(index..index + 1, std::slice::from_mut(&mut digit));
+        digit
+    }
+
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        fill_scaled_digits(&self.inner, self.scale, range, digits);
+    }
+
+    fn get_scale(&self) -> isize {
+        self.scale
+    }
+}
+
+/// Adds up the terms column by column with a multi-digit carry, like
+/// [`SumPadicNumber`](crate::padic_nary::SumPadicNumber), except that terms join the sum as the
+/// digits reach their valuation bound. Digits below the bound of a term are zero, so the columns
+/// that were already computed don't change when it joins.
+///
+/// New terms are made before the cache is taken and without `terms` borrowed, so `term` and
+/// `valuation_bound` can read the digits of the sum that are already known.
+struct SeriesPadicInteger<'a, Digit: Value> {
+    term: Box<dyn Fn(usize) -> PadicNumber<'a, Digit> + 'a>,
+    valuation_bound: Box<dyn Fn(usize) -> isize + 'a>,
+    scale: isize,
+    terms: RefCell<Vec<Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>>>,
+    /// The bound of the last term made.
+    last_bound: Cell<isize>,
+    /// Whether a term is being made, which a read from inside it can't wait for.
+    making_term: Cell<bool>,
+    cache: Cell<SeriesCache<Digit>>,
+}
+
+struct SeriesCache<Digit> {
+    digits: Vec<Digit>,
+    carry: Vec<Digit>,
+}
+
+impl<Digit> Default for SeriesCache<Digit> {
+    fn default() -> Self {
+        SeriesCache { digits: vec![], carry: vec![] }
+    }
+}
+
+impl<'a, Digit: Value + 'a> SeriesPadicInteger<'a, Digit> {
+    /// Makes every term that joins the sum at or below `index`.
+    fn add_terms_up_to(&self, index: isize) {
+        loop {
+            let n = self.terms.borrow().len();
+            let bound = (self.valuation_bound)(n);
+            if bound < self.last_bound.get() {
+                raise(PadicError::DecreasingBound { index: n });
+            }
+            if bound > index {
+                return;
+            }
+            if self.making_term.get() {
+                raise(PadicError::IllFounded);
+            }
+            self.making_term.set(true);
+            let making_term = ComputingGuard(&self.making_term);
+            let term = (self.term)(n);
+            if term.valuation(bound).is_some() {
+                raise(PadicError::TermBelowBound { index: n });
+            }
+            drop(making_term);
+            self.last_bound.set(bound);
+            self.terms.borrow_mut().push(Rc::new(PadicNumberScaler::new(&term, self.scale)));
+        }
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicIntegerAccessor<'a, Digit> for SeriesPadicInteger<'a, Digit> {
+    fn get_integer_digit(&self, index: usize) -> Digit {
+        let mut digit = Digit::zero();
+        self.fill_integer_digits(index..index + 1, std::slice::from_mut(&mut digit));
+        digit
+    }
+
+    fn fill_integer_digits(&self, range: Range<usize>, digits: &mut [Digit]) {
+        self.add_terms_up_to(self.scale + range.end as isize - 1);
+        let terms = self.terms.borrow().clone();
+        let mut cache = CellGuard::take(&self.cache);
+        let cache = &mut cache.value;
+
+        let start = cache.digits.len();
+        if start < range.end {
+            let len = range.end - start;
+            let mut total = cache.carry.clone();
+            let mut block = vec![Digit::zero(); len];
+            for term in &terms {
+                term.fill_integer_digits(start..range.end, &mut block);
+                digit_vec::add_shifted(&mut total, &block, 0);
+            }
+            total.resize(total.len().max(len), Digit::zero());
+            cache.carry = digit_vec::trim(total.split_off(len));
+            cache.digits.extend(total);
+        }
+        digits.copy_from_slice(&cache.digits[range]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::discrete::AddGroupU8;
+    use crate::padic::{PadicAccessor, PadicError, PadicNumber};
+    use crate::padic_periodic::EventuallyPeriodicPadic;
+    use std::cell::RefCell;
+    use std::rc::{Rc, Weak};
+
+    type Padic<'a> = PadicNumber<'a, AddGroupU8<3>>;
+
+    fn power_of_three(n: isize) -> Padic<'static> {
+        PadicNumber::from_exact(EventuallyPeriodicPadic::one().shift(n))
+    }
+
+    /// The series whose term `n` is 3^n times digit `n - lag` of its own sum, from `n = 1` on.
+    fn reading_itself(lag: isize) -> Padic<'static> {
+        let this: Rc<RefCell<Weak<dyn PadicAccessor<'static, AddGroupU8<3>>>>> =
+            Rc::new(RefCell::new(Weak::<EventuallyPeriodicPadic<AddGroupU8<3>>>::new()));
+        let reference = this.clone();
+        let sum = Padic::sum_series(
+            move |n| {
+                if n == 0 {
+                    return power_of_three(0);
+                }
+                let digit = reference.borrow().upgrade().unwrap().get_digit(n as isize - lag);
+                &power_of_three(n as isize) * &Padic::from(EventuallyPeriodicPadic::from_integer(digit.value() as i64))
+            },
+            |n| n as isize,
+        );
+        *this.borrow_mut() = Rc::downgrade(&sum.value);
+        sum
+    }
+
+    #[test]
+    fn geometric_series() {
+        // sum 3^n = 1 / (1 - 3) = -1/2, which is ...111 in base 3
+        let sum = Padic::sum_series(|n| power_of_three(n as isize), |n| n as isize);
+        let mut digits = [AddGroupU8::new(0).unwrap(); 8];
+        sum.try_fill_digits(-2..6, &mut digits).unwrap();
+        let digits: Vec<u8> = digits.iter().map(|digit| digit.value()).collect();
+        assert_eq!(digits, [0, 0, 1, 1, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn term_below_its_bound_is_an_error() {
+        // term 2 is 3^1, which is below the claimed bound of 2
+        let sum = Padic::sum_series(|n| power_of_three(n.min(1) as isize), |n| n as isize);
+        let mut digits = [AddGroupU8::new(0).unwrap(); 2];
+        assert!(sum.try_fill_digits(0..2, &mut digits).is_ok());
+        let mut digits = [AddGroupU8::new(0).unwrap(); 3];
+        assert_eq!(sum.try_fill_digits(0..3, &mut digits), Err(PadicError::TermBelowBound { index: 2 }));
+    }
+
+    #[test]
+    fn decreasing_bound_is_an_error() {
+        let sum = Padic::sum_series(|n| power_of_three(n as isize), |n| if n == 3 { 1 } else { n as isize });
+        let mut digits = [AddGroupU8::new(0).unwrap(); 3];
+        assert_eq!(sum.try_fill_digits(0..3, &mut digits), Err(PadicError::DecreasingBound { index: 3 }));
+    }
+
+    #[test]
+    fn terms_can_read_the_sum_below_their_bound() {
+        // 1 + 3 + 9 + ..., each term made from the digit below it
+        let sum = reading_itself(1);
+        let mut digits = [AddGroupU8::new(0).unwrap(); 6];
+        sum.try_fill_digits(0..6, &mut digits).unwrap();
+        assert!(digits.iter().all(|digit| digit.value() == 1));
+    }
+
+    #[test]
+    fn term_reading_the_sum_at_its_bound_is_ill_founded() {
+        let sum = reading_itself(0);
+        let mut digits = [AddGroupU8::new(0).unwrap(); 2];
+        assert_eq!(sum.try_fill_digits(0..2, &mut digits), Err(PadicError::IllFounded));
+    }
+}
@@ -0,0 +1,150 @@
+use crate::discrete::Value;
+use crate::padic::{checked_index, raise, PadicAccessor, PadicError, PadicNumber};
+use std::ops::{Range, Shl, Shr};
+use std::rc::Rc;
+
+impl<'a, Digit: Value + 'a> PadicNumber<'a, Digit> {
+    /// Multiplies by `p^k` (or divides, for negative `k`) by moving every digit `k` places up.
+    ///
+    /// Panics with [`PadicError::ScaleOverflow`] if that moves the scale past what an `isize`
+    /// can hold: right away for an exact number, and once the scale or a digit is read for a
+    /// lazy one.
+    pub fn shift(&self, k: isize) -> PadicNumber<'a, Digit> {
+        match self.exact() {
+            Some(exact) => PadicNumber::from_exact(exact.shift(k)),
+            None => PadicNumber::new_from_rc(Rc::new(ShiftedPadicNumber::new(self.clone(), k))),
+        }
+    }
+}
+
+impl<'a, Digit: Value + 'a> Shl<isize> for &PadicNumber<'a, Digit> {
+    type Output = PadicNumber<'a, Digit>;
+
+    fn shl(self, rhs: isize) -> Self::Output {
+        self.shift(rhs)
+    }
+}
+
+impl<'a, Digit: Value + 'a> Shl<isize> for PadicNumber<'a, Digit> {
+    type Output = PadicNumber<'a, Digit>;
+
+    fn shl(self, rhs: isize) -> Self::Output {
+        self.shift(rhs)
+    }
+}
+
+impl<'a, Digit: Value + 'a> Shr<isize> for &PadicNumber<'a, Digit> {
+    type Output = PadicNumber<'a, Digit>;
+
+    fn shr(self, rhs: isize) -> Self::Output {
+        self.shift(-rhs)
+    }
+}
+
+impl<'a, Digit: Value + 'a> Shr<isize> for PadicNumber<'a, Digit> {
+    type Output = PadicNumber<'a, Digit>;
+
+    fn shr(self, rhs: isize) -> Self::Output {
+        self.shift(-rhs)
+    }
+}
+
+/// The digits of `inner`, moved `shift` places up.
+///
+/// Digits that would come from below `isize::MIN` in `inner` are zero, like every digit below its
+/// scale. Anything else that overflows raises [`PadicError::ScaleOverflow`].
+pub struct ShiftedPadicNumber<'a, Digit: Value> {
+    inner: PadicNumber<'a, Digit>,
+    shift: isize,
+}
+
+impl<'a, Digit: Value> ShiftedPadicNumber<'a, Digit> {
+    pub(crate) fn new(inner: PadicNumber<'a, Digit>, shift: isize) -> Self {
+        ShiftedPadicNumber { inner, shift }
+    }
+
+    /// The index in `inner` of digit `index`, or `None` if it's below every index there.
+    fn inner_index(&self, index: isize) -> Option<isize> {
+        match index.checked_sub(self.shift) {
+            Some(index) => Some(index),
+            None if self.shift > 0 => None,
+            None => raise(PadicError::ScaleOverflow),
+        }
+    }
+}
+
+impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for ShiftedPadicNumber<'a, Digit> {
+    fn get_digit(&self, index: isize) -> Digit {
+        match self.inner_index(index) {
+            Some(index) => self.inner.value.get_digit(index),
+            None => Digit::zero(),
+        }
+    }
+
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        match (self.inner_index(range.start), self.inner_index(range.end)) {
+            (Some(start), Some(end)) => self.inner.value.fill_digits(start..end, digits),
+            _ => {
+                for (digit, index) in digits.iter_mut().zip(range) {
+                    *digit = self.get_digit(index);
+                }
+            }
+        }
+    }
+
+    fn get_scale(&self) -> isize {
+        checked_index(self.inner.get_scale().checked_add(self.shift))
+    }
+
+    fn settle_scale(&self) -> bool {
+        self.inner.settle_scale()
+    }
+
+    fn operands(&self) -> Vec<PadicNumber<'a, Digit>> {
+        vec![self.inner.clone()]
+    }
+
+    fn take_operands(&mut self) -> Vec<PadicNumber<'a, Digit>> {
+        vec![self.inner.take()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShiftedPadicNumber;
+    use crate::discrete::AddGroupU8;
+    use crate::padic::PadicNumber;
+
+    type Padic<'a> = PadicNumber<'a, AddGroupU8<2>>;
+
+    fn digits(x: &Padic, range: std::ops::Range<isize>) -> Vec<u8> {
+        range.map(|index| x.get_digit(index).value()).collect()
+    }
+
+    #[test]
+    fn huge_shifts_that_come_back() {
+        let three = Padic::from(3u8);
+        let lazy = PadicNumber::new(ShiftedPadicNumber::new(three.clone(), 0));
+        for x in [three, lazy] {
+            let back = x.shift(isize::MAX - 2).shift(-(isize::MAX - 2));
+            assert_eq!(digits(&back, -1..3), vec![0, 1, 1, 0]);
+            let back = x.shift(isize::MIN + 1).shift(isize::MAX);
+            assert_eq!(digits(&back, -1..3), vec![0, 1, 1, 0]);
+            // every digit of `x` is far above these
+            assert_eq!(digits(&x.shift(isize::MAX - 2), -4..4), vec![0; 8]);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "a digit index overflows")]
+    fn exact_shift_past_isize_max_panics() {
+        Padic::from(1u8).shift(isize::MAX).shift(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "a digit index overflows")]
+    fn lazy_shift_past_isize_max_panics() {
+        let one = PadicNumber::new(ShiftedPadicNumber::new(Padic::from(1u8), 0));
+        one.shift(isize::MAX).shift(1).get_scale();
+    }
+}
@@ -1,14 +1,19 @@
 use crate::discrete::Value;
-use crate::padic::{PadicAccessor, PadicIntegerAccessor, PadicNumber, PadicNumberScaler};
+use crate::padic::{
+    fill_scaled_digits, CellGuard, LeadingZeros, PadicAccessor, PadicIntegerAccessor, PadicNumber, PadicNumberScaler,
+};
+use crate::padic_nary::{SumPadicNumber, TermList};
+use crate::padic_periodic::{EventuallyPeriodicPadic, EXACT_DIGIT_LIMIT};
 use std::cell::Cell;
-use std::ops::Sub;
+use std::ops::{Neg, Sub};
+use std::ops::Range;
 use std::rc::Rc;
 
-impl<'a, Digit: Value> Sub for &'a PadicNumber<'a, Digit> {
+impl<'a, 'b, Digit: Value + 'a> Sub<&'b PadicNumber<'a, Digit>> for &'b PadicNumber<'a, Digit> {
     type Output = PadicNumber<'a, Digit>;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        PadicNumber::new_from_rc(Rc::new(SubtractivePadicIntegerScaler::new(self.clone(), rhs.clone())))
+        sub(self.clone(), rhs.clone())
     }
 }
 
@@ -16,40 +21,96 @@ impl<'a, Digit: Value + 'a> Sub for PadicNumber<'a, Digit> {
     type Output = PadicNumber<'a, Digit>;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        PadicNumber::new_from_rc(Rc::new(SubtractivePadicIntegerScaler::new(self, rhs)))
+        sub(self, rhs)
     }
 }
 
+impl<'a, Digit: Value + 'a> Neg for PadicNumber<'a, Digit> {
+    type Output = PadicNumber<'a, Digit>;
+
+    fn neg(self) -> Self::Output {
+        match self.exact() {
+            Some(exact) => PadicNumber::from_exact(-exact),
+            None => sub(PadicNumber::from_exact(EventuallyPeriodicPadic::zero()), self),
+        }
+    }
+}
+
+impl<'a, Digit: Value + 'a> Neg for &PadicNumber<'a, Digit> {
+    type Output = PadicNumber<'a, Digit>;
+
+    fn neg(self) -> Self::Output {
+        -self.clone()
+    }
+}
+
+fn sub<'a, Digit: Value + 'a>(
+    lhs: PadicNumber<'a, Digit>,
+    rhs: PadicNumber<'a, Digit>,
+) -> PadicNumber<'a, Digit> {
+    if let (Some(lhs), Some(rhs)) = (lhs.exact(), rhs.exact())
+        && let Some(difference) = lhs.checked_sub(rhs, EXACT_DIGIT_LIMIT)
+    {
+        return PadicNumber::from_exact(difference);
+    }
+    if Rc::ptr_eq(&lhs.value, &rhs.value) {
+        return PadicNumber::from_exact(EventuallyPeriodicPadic::zero());
+    }
+    if !lhs.settle_scale() || !rhs.settle_scale() {
+        return PadicNumber::build_when_read(vec![lhs, rhs], |operands| sub(operands[0].clone(), operands[1].clone()));
+    }
+    if let Some(terms) = lhs.sum_terms() {
+        return SumPadicNumber::new(TermList::sum_of(Some(terms), rhs, true)).to_dyn();
+    }
+    if let Some(terms) = rhs.sum_terms() {
+        return SumPadicNumber::new(TermList::difference_from(lhs, terms)).to_dyn();
+    }
+    PadicNumber::new_from_rc(Rc::new(SubtractivePadicIntegerScaler::new(lhs, rhs)))
+}
+
 pub struct SubtractivePadicIntegerScaler<'a, Digit: Value> {
     inner: SubtractionPadicInteger<'a, Digit>,
     scale: isize,
-    scale_adjustment: usize,
+    lhs: PadicNumber<'a, Digit>,
+    rhs: PadicNumber<'a, Digit>,
 }
 
 impl<'a, Digit: Value> SubtractivePadicIntegerScaler<'a, Digit> {
     pub(crate) fn new(lhs: PadicNumber<'a, Digit>, rhs: PadicNumber<'a, Digit>) -> Self {
         let scale = lhs.get_scale().min(rhs.get_scale());
         let inner = SubtractionPadicInteger::new(Rc::new(PadicNumberScaler::new(&lhs, scale)), Rc::new(PadicNumberScaler::new(&rhs, scale)));
-        let mut scale_adjustment = 0;
-        while inner.get_integer_digit(scale_adjustment).is_zero() && scale_adjustment < 10 {
-            scale_adjustment += 1;
-        }
-        Self { inner, scale, scale_adjustment }
+        Self { inner, scale, lhs, rhs }
     }
 }
 
 impl<'a, Digit: Value + 'a> PadicAccessor<'a, Digit> for SubtractivePadicIntegerScaler<'a, Digit> {
     fn get_digit(&self, index: isize) -> Digit {
         let adjusted_index = index - self.scale;
-        if adjusted_index < self.scale_adjustment as isize {
+        if adjusted_index < 0 {
             Digit::zero()
         } else {
             self.inner.get_integer_digit(adjusted_index as usize)
         }
     }
 
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [Digit]) {
+        fill_scaled_digits(&self.inner, self.scale, range, digits);
+    }
+
     fn get_scale(&self) -> isize {
-        self.scale + self.scale_adjustment as isize
+        self.scale + self.inner.leading_zeros.get() as isize
+    }
+
+    fn sum_terms(&self) -> Option<Rc<TermList<'a, Digit>>> {
+        Some(TermList::pair_sum(&self.lhs, &self.rhs, true))
+    }
+
+    fn operands(&self) -> Vec<PadicNumber<'a, Digit>> {
+        vec![self.lhs.clone(), self.rhs.clone()]
+    }
+
+    fn take_operands(&mut self) -> Vec<PadicNumber<'a, Digit>> {
+        vec![self.lhs.take(), self.rhs.take()]
     }
 }
 
@@ -57,6 +118,7 @@ pub(crate) struct SubtractionPadicInteger<'a, Digit: Value> {
     lhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
     rhs: Rc<dyn PadicIntegerAccessor<'a, Digit> + 'a>,
     cache: Cell<(Vec<Digit>, bool)>,
+    leading_zeros: LeadingZeros,
 }
 
 impl<'a, Digit: Value> SubtractionPadicInteger<'a, Digit> {
@@ -68,28 +130,38 @@ impl<'a, Digit: Value> SubtractionPadicInteger<'a, Digit> {
             lhs,
             rhs,
             cache: Cell::new((vec![], false)),
+            leading_zeros: LeadingZeros::default(),
         }
     }
 }
 
 impl<'a, Digit: Value + 'a> PadicIntegerAccessor<'a, Digit> for SubtractionPadicInteger<'a, Digit> {
     fn get_integer_digit(&self, index: usize) -> Digit {
-        let (mut digit_cache, mut borrow) = self.cache.take();
-
-        let mut digit = *digit_cache.get(index).unwrap_or(&Digit::zero());
-        for i in digit_cache.len()..=index {
-            let lhs_digit = self.lhs.get_integer_digit(i);
-            let rhs_digit = self.rhs.get_integer_digit(i);
-            let (digit_difference, digit_borrow) = lhs_digit.sub_borrow(rhs_digit);
-            let (full_difference, full_borrow) =
-                digit_difference.sub_borrow(Digit::from_bool(borrow));
-            digit = full_difference;
-            borrow = digit_borrow || full_borrow;
-            digit_cache.push(digit);
-        }
+        let mut digit = Digit::zero();
+        self.fill_integer_digits(index..index + 1, std::slice::from_mut(&mut digit));
+        digit
+    }
 
-        self.cache.set((digit_cache, borrow));
+    fn fill_integer_digits(&self, range: Range<usize>, digits: &mut [Digit]) {
+        let mut cache = CellGuard::take(&self.cache);
+        let (digit_cache, borrow) = &mut cache.value;
 
-        digit
+        if digit_cache.len() < range.end {
+            let start = digit_cache.len();
+            let mut lhs = vec![Digit::zero(); range.end - start];
+            let mut rhs = vec![Digit::zero(); range.end - start];
+            self.lhs.fill_integer_digits(start..range.end, &mut lhs);
+            self.rhs.fill_integer_digits(start..range.end, &mut rhs);
+            for (lhs_digit, rhs_digit) in lhs.into_iter().zip(rhs) {
+                let (digit_difference, digit_borrow) = lhs_digit.sub_borrow(rhs_digit);
+                let (full_difference, full_borrow) =
+                    digit_difference.sub_borrow(Digit::from_bool(*borrow));
+                let digit = full_difference;
+                *borrow = digit_borrow || full_borrow;
+                self.leading_zeros.push(digit_cache.len(), &digit);
+                digit_cache.push(digit);
+            }
+        }
+        digits.copy_from_slice(&digit_cache[range]);
     }
 }
@@ -0,0 +1,64 @@
+//! Fixtures shared by the tests of the arithmetic nodes.
+
+use crate::discrete::AddGroupU8;
+use crate::padic::{PadicAccessor, PadicError, PadicNumber};
+use crate::padic_periodic::EventuallyPeriodicPadic;
+use crate::padic_shift::ShiftedPadicNumber;
+use std::cell::Cell;
+use std::ops::Range;
+use std::rc::Rc;
+
+pub(crate) type Padic<'a, const BASE: u8> = PadicNumber<'a, AddGroupU8<BASE>>;
+pub(crate) type Exact<const BASE: u8> = EventuallyPeriodicPadic<AddGroupU8<BASE>>;
+
+/// Hides `x` behind a lazy node, so arithmetic on it can't take the exact path.
+pub(crate) fn lazy<const BASE: u8>(x: &Exact<BASE>) -> Padic<'static, BASE> {
+    PadicNumber::new(ShiftedPadicNumber::new(PadicNumber::from_exact(x.clone()), 0))
+}
+
+/// Some rationals, with and without powers of the base in them.
+pub(crate) fn operands<const BASE: u8>() -> Vec<Exact<BASE>> {
+    let base = BASE as i64;
+    [(1, 1), (-1, 1), (7, 3), (-5, 12), (2, base * base), (4 * base, 5)]
+        .into_iter()
+        .map(|(numerator, denominator)| &Exact::from_integer(numerator) / &Exact::from_integer(denominator))
+        .collect()
+}
+
+pub(crate) fn digits<const BASE: u8>(x: &Padic<BASE>, range: std::ops::Range<isize>) -> Vec<AddGroupU8<BASE>> {
+    range.map(|index| x.get_digit(index)).collect()
+}
+
+/// The digits in `range` as numbers, or the error reading them raises.
+pub(crate) fn try_digits<const BASE: u8>(x: &Padic<BASE>, range: Range<isize>) -> Result<Vec<u8>, PadicError> {
+    let mut digits = vec![AddGroupU8::new(0).unwrap(); range.len()];
+    x.try_fill_digits(range, &mut digits)?;
+    Ok(digits.iter().map(|digit| digit.value()).collect())
+}
+
+/// `numerator / denominator`, as an exact number.
+pub(crate) fn fraction<const BASE: u8>(numerator: i64, denominator: i64) -> Padic<'static, BASE> {
+    PadicNumber::from_exact(&Exact::from_integer(numerator) / &Exact::from_integer(denominator))
+}
+
+/// Forwards to `inner`, counting how many digits are read.
+pub(crate) struct Counting<const BASE: u8> {
+    pub(crate) inner: Padic<'static, BASE>,
+    pub(crate) reads: Rc<Cell<usize>>,
+}
+
+impl<const BASE: u8> PadicAccessor<'static, AddGroupU8<BASE>> for Counting<BASE> {
+    fn get_digit(&self, index: isize) -> AddGroupU8<BASE> {
+        self.reads.set(self.reads.get() + 1);
+        self.inner.get_digit(index)
+    }
+
+    fn fill_digits(&self, range: Range<isize>, digits: &mut [AddGroupU8<BASE>]) {
+        self.reads.set(self.reads.get() + digits.len());
+        self.inner.fill_digits(range, digits);
+    }
+
+    fn get_scale(&self) -> isize {
+        self.inner.get_scale()
+    }
+}